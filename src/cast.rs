@@ -0,0 +1,130 @@
+use parse_display_derive::Display;
+
+use crate::{
+    datetime::{self, SECONDS_PER_DAY},
+    decimal::Decimal,
+    Ty, Value,
+};
+
+#[derive(Debug, Display, Clone)]
+pub enum CastError {
+    #[display("Cannot cast {from} to {to}.")]
+    Unsupported { from: Ty, to: Ty },
+    #[display("Cannot cast {value} to {ty}.")]
+    InvalidValue { value: Value, ty: Ty },
+    #[display("Type \"{0}\" does not exist.")]
+    NoSuchType(String),
+}
+
+/// Converts a value to another type. Floats and decimals get truncated into ints, strings get
+/// parsed the way literals are written. Nil stays nil.
+pub(crate) fn cast(value: Value, ty: &Ty) -> Result<Value, CastError> {
+    let invalid = |value: &Value| CastError::InvalidValue {
+        value: value.clone(),
+        ty: ty.clone(),
+    };
+
+    let cast = match (&value, ty) {
+        (Value::Nil, _) => Some(Value::Nil),
+        (Value::List(values), Ty::List(ty)) => {
+            return values
+                .iter()
+                .map(|value| cast(value.clone(), ty))
+                .collect::<Result<_, _>>()
+                .map(Value::List);
+        }
+        (_, Ty::Decimal { .. }) => {
+            let decimal = match &value {
+                Value::Decimal(decimal) => Some(*decimal),
+                Value::Int(int) => Some(Decimal::from(*int)),
+                Value::Float(float) => Decimal::from_float(*float),
+                Value::Str(str) => parse_decimal(str.trim()),
+                _ => return Err(unsupported(&value, ty)),
+            };
+
+            decimal.and_then(|decimal| Value::Decimal(decimal).conformed(ty))
+        }
+        (value, ty) if value.ty() == *ty => Some(value.clone()),
+        (Value::Int(int), Ty::Float) => Some(Value::Float(*int as f64)),
+        (Value::Int(int), Ty::Bool) => Some(Value::Bool(*int != 0)),
+        (Value::Float(float), Ty::Int) => {
+            // Truncated toward zero, `as` would saturate instead of failing.
+            let float = float.trunc();
+            (float >= i64::MIN as f64 && float < i64::MAX as f64)
+                .then_some(Value::Int(float as i64))
+        }
+        (Value::Float(float), Ty::Bool) => Some(Value::Bool(*float != 0.0)),
+        (Value::Bool(bool), Ty::Int) => Some(Value::Int(i64::from(*bool))),
+        (Value::Bool(bool), Ty::Float) => Some(Value::Float(f64::from(u8::from(*bool)))),
+        (Value::Decimal(decimal), Ty::Int) => {
+            let int = decimal.mantissa() / 10i128.pow(decimal.scale() as u32);
+            i64::try_from(int).ok().map(Value::Int)
+        }
+        (Value::Decimal(decimal), Ty::Float) => Some(Value::Float(decimal.to_float())),
+        (Value::Date(days), Ty::Timestamp) => days
+            .checked_mul(SECONDS_PER_DAY)
+            .and_then(datetime::timestamp),
+        (Value::Timestamp(seconds), Ty::Date) => {
+            Some(Value::Date(seconds.div_euclid(SECONDS_PER_DAY)))
+        }
+        (Value::Timestamp(seconds), Ty::Time) => {
+            Some(Value::Time(seconds.rem_euclid(SECONDS_PER_DAY)))
+        }
+        (Value::Bytes(bytes), Ty::Str) => String::from_utf8(bytes.clone()).ok().map(Value::Str),
+        (Value::Enum(..), Ty::Str) => Some(Value::Str(value.variant().to_owned())),
+        (
+            Value::Int(_)
+            | Value::Float(_)
+            | Value::Bool(_)
+            | Value::Decimal(_)
+            | Value::Date(_)
+            | Value::Time(_)
+            | Value::Timestamp(_)
+            | Value::Interval(_),
+            Ty::Str,
+        ) => Some(Value::Str(value.to_string())),
+        (Value::Str(str), _) => parse(str.trim(), ty)?,
+        _ => return Err(unsupported(&value, ty)),
+    };
+
+    cast.ok_or_else(|| invalid(&value))
+}
+
+/// Parses a string into a value of type `ty`, `None` if it is not valid and an error if
+/// strings cannot be cast to the type.
+fn parse(str: &str, ty: &Ty) -> Result<Option<Value>, CastError> {
+    Ok(match ty {
+        Ty::Int => str.parse().ok().map(Value::Int),
+        Ty::Float => str.parse().ok().map(Value::Float),
+        Ty::Bool => match str {
+            "true" => Some(Value::Bool(true)),
+            "false" => Some(Value::Bool(false)),
+            _ => None,
+        },
+        Ty::Bytes => Some(Value::Bytes(str.as_bytes().to_vec())),
+        Ty::Date => datetime::parse_date(str).and_then(datetime::date),
+        Ty::Time => datetime::parse_time(str).map(Value::Time),
+        Ty::Timestamp => datetime::parse_timestamp(str).and_then(datetime::timestamp),
+        Ty::Interval => datetime::parse_interval(str).map(Value::Interval),
+        Ty::Enum(_) => Value::Str(str.to_owned()).conformed(ty),
+        _ => return Err(unsupported(&Value::Str(str.to_owned()), ty)),
+    })
+}
+
+/// Parses a decimal with an optional leading `-`.
+fn parse_decimal(str: &str) -> Option<Decimal> {
+    match str.strip_prefix('-') {
+        Some(str) => {
+            let decimal = Decimal::parse(str)?;
+            Some(Decimal::new(-decimal.mantissa(), decimal.scale()))
+        }
+        None => Decimal::parse(str),
+    }
+}
+
+fn unsupported(value: &Value, ty: &Ty) -> CastError {
+    CastError::Unsupported {
+        from: value.ty(),
+        to: ty.clone(),
+    }
+}
@@ -1,6 +1,9 @@
 use std::collections::HashMap;
 
-use crate::{token::Token, Ty};
+use crate::{
+    token::{Keyword, Token},
+    Ty,
+};
 
 use super::Value;
 
@@ -9,32 +12,105 @@ pub enum Command {
     New {
         identifier: String,
         definitions: Vec<ColumnDefinition>,
+        key: Key,
+    },
+    /// Declares an enum type, usable as a column type.
+    NewEnum {
+        identifier: String,
+        variants: Vec<String>,
     },
     Insert {
         identifier: String,
-        insertions: Vec<HashMap<String, Value>>,
+        /// Values to insert by column, they get evaluated without a row.
+        insertions: Vec<HashMap<String, Expression>>,
+    },
+    /// Inserts the rows selected by a query, its columns are matched to the table's by name. A
+    /// selected `Id` column is left out when the table assigns its own ids.
+    InsertFrom {
+        identifier: String,
+        query: Box<Command>,
     },
     Get {
         identifier: String,
         selections: Vec<Selection>,
+        distinct: bool,
         filter: Option<Expression>,
     },
     Remove {
         identifier: String,
         expression: Expression,
     },
+    /// Two queries combined with a set operation.
+    Combine {
+        lhs: Box<Command>,
+        operation: SetOperation,
+        rhs: Box<Command>,
+    },
+}
+
+#[derive(Debug, Clone, Copy)]
+pub enum SetOperation {
+    Union,
+    UnionAll,
+    Intersect,
+    Except,
 }
 
 #[derive(Debug, Clone)]
 pub struct ColumnDefinition {
     pub identifier: String,
     pub optional: bool,
-    pub ty: Ty,
+    pub ty: TyDefinition,
+    pub unique: bool,
+    pub default: Option<Value>,
+    pub check: Option<Expression>,
+    pub references: Option<Reference>,
+}
+
+/// A type as written, types declared with `new type` are only known by name until the command
+/// gets run.
+#[derive(Debug, Clone)]
+pub enum TyDefinition {
+    Ty(Ty),
+    Named(String),
+    List(Box<TyDefinition>),
+}
+
+/// How rows of a table are identified.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub enum Key {
+    /// An `Id` column assigned on insertion.
+    Id,
+    /// Columns whose combined values are unique.
+    Primary(Vec<String>),
+    #[default]
+    None,
+}
+
+/// A column holding the key of a row in another table.
+#[derive(Debug, Clone)]
+pub struct Reference {
+    pub table: String,
+    pub on_remove: OnRemove,
+}
+
+/// What happens to referencing rows when the referenced row gets removed.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum OnRemove {
+    Restrict,
+    Cascade,
+    SetNil,
 }
 
 #[derive(Debug, Clone)]
 pub enum Selection {
-    Identifier { identifier: String },
+    Identifier {
+        identifier: String,
+    },
+    Expression {
+        expression: Expression,
+        identifier: String,
+    },
     All,
 }
 
@@ -46,11 +122,21 @@ crate::operator! {
     | Less
     | LessEq
     | More
-    | MoreEq,
+    | MoreEq
+    | Like
+    | ILike
+    | Contains
+    | IContains
+    | StartsWith
+    | IStartsWith
+    | EndsWith
+    | IEndsWith,
     #[2]
     Add | Sub,
     #[3]
-    Mul | Div
+    Mul | Div,
+    #[4]
+    Index
 }
 
 #[derive(Debug, Clone, Copy)]
@@ -64,6 +150,7 @@ impl TryFrom<&Token> for Operator {
             Token::Minus => Operator::Sub,
             Token::Star => Operator::Mul,
             Token::Slash => Operator::Div,
+            Token::LeftSquare => Operator::Index,
             Token::DoubleEq => Operator::Eq,
             Token::Less => Operator::Less,
             Token::LessEq => Operator::LessEq,
@@ -71,6 +158,14 @@ impl TryFrom<&Token> for Operator {
             Token::MoreEq => Operator::MoreEq,
             Token::DoubleAmpersand => Operator::And,
             Token::DoublePipe => Operator::Or,
+            Token::Keyword(Keyword::Like) => Operator::Like,
+            Token::Keyword(Keyword::ILike) => Operator::ILike,
+            Token::Keyword(Keyword::Contains) => Operator::Contains,
+            Token::Keyword(Keyword::IContains) => Operator::IContains,
+            Token::Keyword(Keyword::Starts) => Operator::StartsWith,
+            Token::Keyword(Keyword::IStarts) => Operator::IStartsWith,
+            Token::Keyword(Keyword::Ends) => Operator::EndsWith,
+            Token::Keyword(Keyword::IEnds) => Operator::IEndsWith,
             _ => return Err(NoSuchOperatorError),
         })
     }
@@ -81,30 +176,86 @@ pub enum Expression {
     Value(Value),
     Identifier(String),
     Enclosed(Box<Expression>),
+    Call {
+        identifier: String,
+        arguments: Vec<Expression>,
+    },
+    /// A nested "get" command.
+    Query(Box<Command>),
     Operation {
         lhs: Box<Expression>,
         operator: Operator,
         rhs: Box<Expression>,
     },
+    In {
+        expression: Box<Expression>,
+        list: Vec<Expression>,
+        negated: bool,
+    },
+    Between {
+        expression: Box<Expression>,
+        low: Box<Expression>,
+        high: Box<Expression>,
+        negated: bool,
+    },
+    /// `cast(expression as ty)` or `expression::ty`.
+    Cast {
+        expression: Box<Expression>,
+        ty: TyDefinition,
+    },
 }
 
 impl Expression {
     pub fn extended(self, operator: Operator, rhs: Expression) -> Self {
+        self.extended_with(operator.precedence(), |lhs| Expression::Operation {
+            lhs: Box::new(lhs),
+            operator,
+            rhs: Box::new(rhs),
+        })
+    }
+
+    /// Columns the expression reads, leaving out those read by nested queries.
+    pub fn identifiers(&self) -> Vec<&str> {
+        let mut identifiers = Vec::new();
+        let mut pending = vec![self];
+        while let Some(expression) = pending.pop() {
+            match expression {
+                Expression::Value(_) | Expression::Query(_) => {}
+                Expression::Identifier(identifier) => identifiers.push(identifier.as_str()),
+                Expression::Enclosed(expression) | Expression::Cast { expression, .. } => {
+                    pending.push(expression)
+                }
+                Expression::Call { arguments, .. } => pending.extend(arguments),
+                Expression::Operation { lhs, rhs, .. } => pending.extend([&**lhs, &**rhs]),
+                Expression::In {
+                    expression, list, ..
+                } => {
+                    pending.push(expression);
+                    pending.extend(list);
+                }
+                Expression::Between {
+                    expression,
+                    low,
+                    high,
+                    ..
+                } => pending.extend([&**expression, &**low, &**high]),
+            }
+        }
+
+        identifiers
+    }
+
+    /// Replaces the right-most operand of operators with a lower `precedence` using `f`.
+    pub fn extended_with(self, precedence: u8, f: impl FnOnce(Expression) -> Expression) -> Self {
         match self {
-            Expression::Operation {
-                lhs,
-                operator: self_operator,
-                rhs: self_right,
-            } if operator.precedence() > self_operator.precedence() => Expression::Operation {
-                lhs,
-                operator: self_operator,
-                rhs: Box::new(self_right.extended(operator, rhs)),
-            },
-            _ => Expression::Operation {
-                lhs: Box::new(self),
-                operator,
-                rhs: Box::new(rhs),
-            },
+            Expression::Operation { lhs, operator, rhs } if precedence > operator.precedence() => {
+                Expression::Operation {
+                    lhs,
+                    operator,
+                    rhs: Box::new(rhs.extended_with(precedence, f)),
+                }
+            }
+            _ => f(self),
         }
     }
 }
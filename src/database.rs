@@ -2,21 +2,37 @@ use parse_display_derive::Display;
 use terrors::OneOf;
 
 use crate::{
-    command::{ColumnDefinition, Command, Expression, Operator, Selection},
-    Ty, Value,
+    cast::{self, CastError},
+    command::{
+        ColumnDefinition, Command, Expression, Key, OnRemove, Operator, Reference, Selection,
+        SetOperation, TyDefinition,
+    },
+    datetime,
+    decimal::{Decimal, MAX_PRECISION},
+    function::{self, FunctionCallError, Functions, Signature},
+    EnumTy, Ty, Value,
+};
+use std::{
+    collections::{BTreeSet, HashMap, HashSet},
+    fmt::Display,
+    rc::Rc,
 };
-use std::{collections::HashMap, fmt::Display};
 
 #[derive(Debug, Clone)]
 pub enum CommandRunOutput {
     RowsInserted {
         identifier: String,
         count: usize,
+        /// Ids assigned to the inserted rows, empty for tables without an `Id` column.
+        ids: Vec<i64>,
         errs: Vec<InsertError>,
     },
     TableCreated {
         identifier: String,
     },
+    TypeCreated {
+        identifier: String,
+    },
     Selection {
         table: Table,
     },
@@ -32,6 +48,7 @@ impl Display for CommandRunOutput {
                 identifier,
                 count,
                 errs,
+                ..
             } => {
                 for err in errs {
                     writeln!(f, "Insertion failed: {err}")?;
@@ -47,6 +64,9 @@ impl Display for CommandRunOutput {
             CommandRunOutput::TableCreated { identifier } => {
                 write!(f, "Table \"{identifier}\" created.")
             }
+            CommandRunOutput::TypeCreated { identifier } => {
+                write!(f, "Type \"{identifier}\" created.")
+            }
             CommandRunOutput::Selection { table } => write!(f, "{table}"),
             CommandRunOutput::Removed { count } => {
                 write!(
@@ -73,112 +93,547 @@ pub enum InsertionError {
     IncorrectTy { column: String, ty: Ty },
     #[display("Column \"{column}\" is not optional.")]
     NonOptionalColumn { column: String },
+    #[display("Column \"{column}\" already contains this value.")]
+    NotUnique { column: String },
+    #[display("Column \"{column}\" does not pass its check.")]
+    CheckViolation { column: String },
+    #[display("A row with the same key already exists.")]
+    DuplicateKey,
+    #[display("Column \"{column}\" is selected more than once.")]
+    DuplicateColumn { column: String },
+}
+
+#[derive(Debug, Display, Clone)]
+pub enum ReferenceError {
+    #[display("Column \"{column}\" references a row that does not exist in table \"{table}\".")]
+    NoSuchRow { column: String, table: String },
+    #[display("Rows are still referenced by column \"{column}\" of table \"{table}\".")]
+    StillReferenced { column: String, table: String },
+    #[display("Table \"{table}\" does not have a single column key to reference.")]
+    NoSingleKey { table: String },
+}
+
+#[derive(Debug, Display, Clone)]
+pub enum TypeError {
+    #[display("Type \"{0}\" does not exist.")]
+    NoSuchType(String),
+    #[display("Type \"{0}\" already exists.")]
+    AlreadyExists(String),
+    #[display("Variant \"{0}\" is declared more than once.")]
+    DuplicateVariant(String),
+}
+
+#[derive(Debug, Display, Clone)]
+pub enum TableError {
+    #[display("Table \"{0}\" already exists.")]
+    AlreadyExists(String),
+    #[display("Column \"{0}\" is declared more than once.")]
+    DuplicateColumn(String),
+    #[display("Column \"{0}\" has to be optional to be set to nil on removal.")]
+    SetNilOnNonOptional(String),
+    #[display("Column \"{column}\" references a key of type: {ty}.")]
+    ReferenceTyMismatch { column: String, ty: Ty },
+    #[display("Key column \"{0}\" cannot be optional.")]
+    OptionalKey(String),
 }
 
 const ID_IDENTIFIER: &str = "Id";
 
+pub type RunError = OneOf<(
+    NoSuchTableError,
+    InsertionError,
+    IdInsertError,
+    EvaluateError,
+    ReferenceError,
+    NoSuchColumnError,
+    TypeError,
+    TableError,
+)>;
+
+#[derive(Default)]
 pub struct Database {
     tables: HashMap<String, Table>,
+    /// Enum types declared with `new type`.
+    types: HashMap<String, Rc<EnumTy>>,
+    functions: Functions,
+    /// Whether inserted numbers need to be of the exact type of their column.
+    strict: bool,
 }
 
 impl Database {
     pub fn new() -> Self {
         Self {
             tables: HashMap::new(),
+            types: HashMap::new(),
+            functions: Functions::new(),
+            strict: false,
         }
     }
 
-    pub fn run_command(
+    /// Registers a function callable from expressions, its arguments and result get checked
+    /// against the `signature`.
+    pub fn register_function(
         &mut self,
-        command: Command,
-    ) -> Result<
-        CommandRunOutput,
-        OneOf<(
-            NoSuchTableError,
-            InsertionError,
-            NoSuchColumnError,
-            ExpectedBoolError,
-            CannotEvaluateError,
-            IdInsertError,
-        )>,
-    > {
+        identifier: impl Into<String>,
+        signature: Signature,
+        function: impl Fn(&[Value]) -> Value + 'static,
+    ) {
+        self.functions.register(identifier, signature, function);
+    }
+
+    /// In strict mode inserted values need to be of the type of their column, otherwise numbers
+    /// get converted when nothing is lost, like `35.0` into an int column.
+    pub fn set_strict(&mut self, strict: bool) {
+        self.strict = strict;
+    }
+
+    /// Converts a value to be stored in a column of type `ty`, `None` if it does not fit.
+    fn stored(&self, value: Value, ty: &Ty) -> Option<Value> {
+        if self.strict {
+            value.conformed(ty)
+        } else {
+            value.coerced(ty)
+        }
+    }
+
+    /// Looks up a row by the values of the table's key columns, in the order they were
+    /// declared.
+    pub fn lookup(
+        &self,
+        identifier: &str,
+        key: &[Value],
+    ) -> Result<Option<HashMap<String, Value>>, NoSuchTableError> {
+        let Some(table) = self.tables.get(identifier) else {
+            return Err(NoSuchTableError(identifier.to_owned()));
+        };
+
+        Ok(table.find_key(key).and_then(|index| table.row(index)))
+    }
+
+    pub fn run_command(&mut self, command: Command) -> Result<CommandRunOutput, RunError> {
         match command {
             Command::New {
                 identifier,
                 definitions,
+                key,
             } => {
-                let mut columns = vec![Column {
-                    identifier: ID_IDENTIFIER.to_owned(),
-                    ty: Ty::Int,
-                    optional: false,
-                    values: Vec::new(),
-                }];
+                if self.tables.contains_key(&identifier) {
+                    return Err(OneOf::new(TableError::AlreadyExists(identifier)));
+                }
+
+                let mut columns = Vec::new();
+                if key == Key::Id {
+                    columns.push(Column {
+                        identifier: ID_IDENTIFIER.to_owned(),
+                        ty: Ty::Int,
+                        optional: false,
+                        unique: true,
+                        default: None,
+                        check: None,
+                        references: None,
+                        values: Vec::new(),
+                    });
+                }
 
                 for ColumnDefinition {
                     identifier,
                     optional,
                     ty,
+                    unique,
+                    default,
+                    check,
+                    references,
                 } in definitions
                 {
+                    if columns.iter().any(|column| column.identifier == identifier) {
+                        return Err(OneOf::new(TableError::DuplicateColumn(identifier)));
+                    }
+
+                    let ty = self.resolved(ty).map_err(OneOf::new)?;
+                    let default = match default {
+                        Some(Value::Nil) if optional => Some(Value::Nil),
+                        Some(default) => {
+                            let Some(default) = self.stored(default, &ty) else {
+                                return Err(OneOf::new(InsertionError::IncorrectTy {
+                                    column: identifier,
+                                    ty,
+                                }));
+                            };
+
+                            Some(default)
+                        }
+                        None => None,
+                    };
+
+                    if let Some(Reference { on_remove, .. }) = &references {
+                        if *on_remove == OnRemove::SetNil && !optional {
+                            return Err(OneOf::new(TableError::SetNilOnNonOptional(identifier)));
+                        }
+                    }
+
                     columns.push(Column {
                         identifier,
                         ty,
                         optional,
+                        unique,
+                        default,
+                        check,
+                        references,
                         values: Vec::new(),
                     });
                 }
 
-                self.tables.insert(identifier.clone(), Table { columns });
+                if let Key::Primary(key) = &key {
+                    for identifier in key {
+                        let Some(column) = columns
+                            .iter()
+                            .find(|column| column.identifier == *identifier)
+                        else {
+                            return Err(OneOf::new(NoSuchColumnError(identifier.clone())));
+                        };
+
+                        if column.optional {
+                            return Err(OneOf::new(TableError::OptionalKey(identifier.clone())));
+                        }
+                    }
+                }
+
+                // Checks can read any of the columns, including those declared after them.
+                for column in &columns {
+                    let Some(check) = &column.check else {
+                        continue;
+                    };
+
+                    if let Some(identifier) = check.identifiers().into_iter().find(|identifier| {
+                        !columns
+                            .iter()
+                            .any(|column| column.identifier == *identifier)
+                    }) {
+                        return Err(OneOf::new(NoSuchColumnError(identifier.to_owned())));
+                    }
+                }
+
+                let table = Table {
+                    columns,
+                    key,
+                    sequence: 0,
+                };
+                for column in &table.columns {
+                    let Some(Reference {
+                        table: referenced, ..
+                    }) = &column.references
+                    else {
+                        continue;
+                    };
+
+                    // A table is allowed to reference itself.
+                    let referenced_table = if *referenced == identifier {
+                        &table
+                    } else {
+                        self.tables
+                            .get(referenced)
+                            .ok_or_else(|| OneOf::new(NoSuchTableError(referenced.clone())))?
+                    };
+
+                    let Some(key) = referenced_table.key_column() else {
+                        return Err(OneOf::new(ReferenceError::NoSingleKey {
+                            table: referenced.clone(),
+                        }));
+                    };
+
+                    if column.ty != key.ty {
+                        return Err(OneOf::new(TableError::ReferenceTyMismatch {
+                            column: column.identifier.clone(),
+                            ty: key.ty.clone(),
+                        }));
+                    }
+                }
+
+                self.tables.insert(identifier.clone(), table);
                 Ok(CommandRunOutput::TableCreated { identifier })
             }
+            Command::NewEnum {
+                identifier,
+                variants,
+            } => {
+                if self.types.contains_key(&identifier) {
+                    return Err(OneOf::new(TypeError::AlreadyExists(identifier)));
+                }
+
+                let mut seen = HashSet::new();
+                for variant in &variants {
+                    if !seen.insert(variant) {
+                        return Err(OneOf::new(TypeError::DuplicateVariant(variant.clone())));
+                    }
+                }
+
+                let enum_ty = EnumTy {
+                    identifier: identifier.clone(),
+                    variants,
+                };
+                self.types.insert(identifier.clone(), Rc::new(enum_ty));
+                Ok(CommandRunOutput::TypeCreated { identifier })
+            }
             Command::Insert {
                 identifier,
                 insertions,
             } => {
-                let Some(table) = self.tables.get_mut(&identifier) else {
+                if !self.tables.contains_key(&identifier) {
                     return Err(OneOf::new(NoSuchTableError(identifier)));
-                };
+                }
 
                 let mut errs = Vec::new();
                 let mut count = 0;
+                let mut ids = Vec::new();
                 for insertion in insertions {
-                    let Err(err) = table.insert(insertion) else {
-                        count += 1;
-                        continue;
-                    };
+                    let inserted = Table::evaluated(insertion, self)
+                        .map_err(OneOf::new)
+                        .and_then(|row| self.insert(&identifier, row));
+
+                    match inserted {
+                        Ok(id) => {
+                            ids.extend(id);
+                            count += 1;
+                        }
+                        Err(err) => errs.push(err),
+                    }
+                }
+
+                Ok(CommandRunOutput::RowsInserted {
+                    identifier,
+                    count,
+                    ids,
+                    errs,
+                })
+            }
+            Command::InsertFrom { identifier, query } => {
+                if !self.tables.contains_key(&identifier) {
+                    return Err(OneOf::new(NoSuchTableError(identifier)));
+                }
+
+                // The query is run in full first, so that it does not see the inserted rows.
+                let mut table = self.query(*query).map_err(OneOf::new)?;
+                let target = &self.tables[&identifier];
+                if target.key == Key::Id {
+                    table
+                        .columns
+                        .retain(|column| column.identifier != ID_IDENTIFIER);
+                }
+
+                if let Some(column) = table
+                    .columns
+                    .iter()
+                    .find(|column| target.column(&column.identifier).is_none())
+                {
+                    return Err(OneOf::new(NoSuchColumnError(column.identifier.clone())));
+                }
 
-                    errs.push(err);
+                for (index, column) in table.columns.iter().enumerate() {
+                    if table.columns[..index]
+                        .iter()
+                        .any(|other| other.identifier == column.identifier)
+                    {
+                        return Err(OneOf::new(InsertionError::DuplicateColumn {
+                            column: column.identifier.clone(),
+                        }));
+                    }
+                }
+
+                let mut errs = Vec::new();
+                let mut count = 0;
+                let mut ids = Vec::new();
+                for index in 0..table.height() {
+                    let row = table.row(index).expect("index is within the height");
+                    match self.insert(&identifier, row) {
+                        Ok(id) => {
+                            ids.extend(id);
+                            count += 1;
+                        }
+                        Err(err) => errs.push(err),
+                    }
                 }
 
                 Ok(CommandRunOutput::RowsInserted {
                     identifier,
                     count,
+                    ids,
                     errs,
                 })
             }
-            Command::Get {
+            command @ (Command::Get { .. } | Command::Combine { .. }) => {
+                let table = self.query(command).map_err(OneOf::new)?;
+                Ok(CommandRunOutput::Selection { table })
+            }
+            Command::Remove {
                 identifier,
-                selections,
-                filter,
+                expression,
             } => {
                 let Some(table) = self.tables.get(&identifier) else {
                     return Err(OneOf::new(NoSuchTableError(identifier)));
                 };
 
-                let table = table.get(selections, filter).map_err(OneOf::broaden)?;
-                Ok(CommandRunOutput::Selection { table })
+                let indices = table.find(expression, self).map_err(OneOf::new)?;
+                let count = self.remove(&identifier, indices).map_err(OneOf::new)?;
+
+                Ok(CommandRunOutput::Removed { count })
             }
-            Command::Remove {
+        }
+    }
+
+    /// Validates a row and inserts it into a table, returning its id if the table assigns ids.
+    fn insert(
+        &mut self,
+        identifier: &str,
+        row: HashMap<String, Value>,
+    ) -> Result<Option<i64>, InsertError> {
+        // Validation needs the whole database, so the table is only borrowed mutably for the
+        // insertion itself.
+        let table = &self.tables[identifier];
+        let row = table.validated(row, self)?;
+        let id = match (&table.key, row.get(ID_IDENTIFIER)) {
+            (Key::Id, Some(Value::Int(id))) => Some(*id),
+            _ => None,
+        };
+
+        self.tables
+            .get_mut(identifier)
+            .expect("table was found above")
+            .push(row);
+        Ok(id)
+    }
+
+    /// Removes rows from a table along with the rows referencing them with `on remove cascade`.
+    /// Fails without removing anything if any of them is still referenced with
+    /// `on remove restrict`. Returns the count of rows removed from the table itself.
+    fn remove(&mut self, identifier: &str, indices: Vec<usize>) -> Result<usize, ReferenceError> {
+        let mut removals = HashMap::<String, BTreeSet<usize>>::new();
+        let mut pending = vec![(identifier.to_owned(), indices)];
+        while let Some((identifier, indices)) = pending.pop() {
+            let removed = removals.entry(identifier.clone()).or_default();
+            let indices = indices
+                .into_iter()
+                .filter(|index| removed.insert(*index))
+                .collect::<Vec<_>>();
+
+            if indices.is_empty() {
+                continue;
+            }
+
+            let keys = self.tables[&identifier].keys(&indices);
+            for (child, column, on_remove) in self.references(&identifier) {
+                if on_remove == OnRemove::Cascade {
+                    pending.push((child.clone(), self.tables[child].referencing(column, &keys)));
+                }
+            }
+        }
+
+        // Cascades are resolved, the rest of the referencing rows stay.
+        let mut nils = Vec::new();
+        for (identifier, removed) in &removals {
+            let keys = self.tables[identifier].keys(&removed.iter().copied().collect::<Vec<_>>());
+            for (child, column, on_remove) in self.references(identifier) {
+                let rows = self.tables[child]
+                    .referencing(column, &keys)
+                    .into_iter()
+                    .filter(|row| !removals.get(child).is_some_and(|rows| rows.contains(row)))
+                    .collect::<Vec<_>>();
+
+                match on_remove {
+                    _ if rows.is_empty() => {}
+                    OnRemove::Restrict => {
+                        return Err(ReferenceError::StillReferenced {
+                            column: column.to_owned(),
+                            table: child.clone(),
+                        })
+                    }
+                    OnRemove::SetNil => nils.push((child.clone(), column.to_owned(), rows)),
+                    OnRemove::Cascade => unreachable!("cascaded rows are removed"),
+                }
+            }
+        }
+
+        for (identifier, column, rows) in nils {
+            let column = self
+                .tables
+                .get_mut(&identifier)
+                .expect("?")
+                .columns
+                .iter_mut()
+                .find(|Column { identifier, .. }| *identifier == column)
+                .expect("?");
+
+            for row in rows {
+                column.values[row] = Value::Nil;
+            }
+        }
+
+        let count = removals[identifier].len();
+        for (identifier, removed) in removals {
+            self.tables
+                .get_mut(&identifier)
+                .expect("?")
+                .remove_rows(&removed.into_iter().collect::<Vec<_>>());
+        }
+
+        Ok(count)
+    }
+
+    /// The type a column definition refers to.
+    fn resolved(&self, ty: TyDefinition) -> Result<Ty, TypeError> {
+        match ty {
+            TyDefinition::Ty(ty) => Ok(ty),
+            TyDefinition::Named(identifier) => match self.types.get(&identifier) {
+                Some(enum_ty) => Ok(Ty::Enum(enum_ty.clone())),
+                None => Err(TypeError::NoSuchType(identifier)),
+            },
+            TyDefinition::List(ty) => Ok(Ty::List(Box::new(self.resolved(*ty)?))),
+        }
+    }
+
+    /// Columns referencing a table, along with the table they're in.
+    fn references<'a>(
+        &'a self,
+        identifier: &'a str,
+    ) -> impl Iterator<Item = (&'a String, &'a str, OnRemove)> + 'a {
+        self.tables.iter().flat_map(move |(child, table)| {
+            table
+                .columns
+                .iter()
+                .filter_map(move |column| match &column.references {
+                    Some(Reference { table, on_remove }) if table == identifier => {
+                        Some((child, column.identifier.as_str(), *on_remove))
+                    }
+                    _ => None,
+                })
+        })
+    }
+
+    /// Runs a "get" command or a combination of them, which can also be nested in expressions.
+    pub(crate) fn query(&self, command: Command) -> Result<Table, EvaluateError> {
+        match command {
+            Command::Get {
                 identifier,
-                expression,
+                selections,
+                distinct,
+                filter,
             } => {
-                let Some(table) = self.tables.get_mut(&identifier) else {
+                let Some(table) = self.tables.get(&identifier) else {
                     return Err(OneOf::new(NoSuchTableError(identifier)));
                 };
 
-                let count = table.remove(expression).map_err(OneOf::broaden)?;
-                Ok(CommandRunOutput::Removed { count })
+                table.get(selections, distinct, filter, self)
             }
+            Command::Combine {
+                lhs,
+                operation,
+                rhs,
+            } => {
+                let lhs = self.query(*lhs)?;
+                let rhs = self.query(*rhs)?;
+                lhs.combined(operation, rhs)
+                    .map_err(|err| OneOf::new(QueryError::new(err)))
+            }
+            _ => unreachable!("only \"get\" commands are queries"),
         }
     }
 }
@@ -207,6 +662,16 @@ impl Display for CannotEvaluateError {
             | Operator::MoreEq => {
                 write!(f, "compare")?;
             }
+            Operator::Like
+            | Operator::ILike
+            | Operator::Contains
+            | Operator::IContains
+            | Operator::StartsWith
+            | Operator::IStartsWith
+            | Operator::EndsWith
+            | Operator::IEndsWith => {
+                write!(f, "match")?;
+            }
             Operator::Add => {
                 write!(f, "add")?;
             }
@@ -219,6 +684,9 @@ impl Display for CannotEvaluateError {
             Operator::Div => {
                 write!(f, "divide")?;
             }
+            Operator::Index => {
+                write!(f, "index")?;
+            }
         }
 
         write!(f, " {} and {}", self.lhs.ty(), self.rhs.ty())
@@ -233,21 +701,71 @@ pub struct ExpectedBoolError;
 #[display("Expected a single value.")]
 pub struct ExpectedValueError;
 
+#[derive(Debug, Display, Clone)]
+pub enum IncompatibleQueriesError {
+    #[display("Combined queries select a different number of columns.")]
+    ColumnCount,
+    #[display("Combined queries select types: {lhs} and {rhs}, for column {index}.")]
+    ColumnTy { index: usize, lhs: Ty, rhs: Ty },
+}
+
+/// Errors caused by what a query results in.
+pub type QueryError = OneOf<(ExpectedValueError, IncompatibleQueriesError)>;
+
+#[derive(Debug, Display, Clone, Copy)]
+#[display("Division by zero.")]
+pub struct DivisionByZeroError;
+
+#[derive(Debug, Display, Clone, Copy)]
+#[display("Date out of range, dates are within the years 0 and 9999.")]
+pub struct DateRangeError;
+
+/// Errors caused by arithmetic without a result, integer overflow is separate as functions
+/// report it too.
+pub type ArithmeticError = OneOf<(DivisionByZeroError, DateRangeError)>;
+
+#[derive(Debug, Display, Clone, Copy)]
+#[display("Integer overflow.")]
+pub struct IntegerOverflowError;
+
+pub type EvaluateError = OneOf<(
+    CannotEvaluateError,
+    NoSuchColumnError,
+    NoSuchTableError,
+    ExpectedBoolError,
+    ArithmeticError,
+    IntegerOverflowError,
+    FunctionCallError,
+    QueryError,
+    CastError,
+)>;
+
 #[derive(Debug, Display, Clone, Copy)]
 #[display("Column \"id\" is only inserted automatically.")]
 pub struct IdInsertError;
 
-pub type InsertError = OneOf<(InsertionError, NoSuchColumnError, IdInsertError)>;
+pub type InsertError = OneOf<(
+    InsertionError,
+    NoSuchColumnError,
+    IdInsertError,
+    EvaluateError,
+    ReferenceError,
+)>;
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Default)]
 pub struct Table {
     columns: Vec<Column>,
+    key: Key,
+    /// The `Id` of the next inserted row, ids of removed rows are never reused.
+    sequence: i64,
 }
 
 impl Table {
     pub fn new() -> Self {
         Self {
             columns: Vec::new(),
+            key: Key::None,
+            sequence: 0,
         }
     }
 
@@ -299,9 +817,13 @@ impl Table {
     pub fn get(
         &self,
         selections: Vec<Selection>,
+        distinct: bool,
         filter: Option<Expression>,
-    ) -> Result<Table, OneOf<(NoSuchColumnError, CannotEvaluateError, ExpectedBoolError)>> {
+        database: &Database,
+    ) -> Result<Table, EvaluateError> {
         let mut columns = Vec::new();
+        // How the values of every column are computed from a row.
+        let mut expressions = Vec::new();
         for selection in &selections {
             match selection {
                 Selection::Identifier { identifier } => {
@@ -317,10 +839,32 @@ impl Table {
 
                     columns.push(Column {
                         identifier: identifier.clone(),
-                        ty: *ty,
+                        ty: ty.clone(),
                         optional: *optional,
+                        unique: false,
+                        default: None,
+                        check: None,
+                        references: None,
+                        values: Vec::new(),
+                    });
+                    expressions.push(Expression::Identifier(identifier.clone()));
+                }
+                Selection::Expression {
+                    expression,
+                    identifier,
+                } => {
+                    // The type gets inferred from the values.
+                    columns.push(Column {
+                        identifier: identifier.clone(),
+                        ty: Ty::Nil,
+                        optional: false,
+                        unique: false,
+                        default: None,
+                        check: None,
+                        references: None,
                         values: Vec::new(),
-                    })
+                    });
+                    expressions.push(expression.clone());
                 }
                 Selection::All => {
                     for Column {
@@ -332,68 +876,304 @@ impl Table {
                     {
                         columns.push(Column {
                             identifier: identifier.clone(),
-                            ty: *ty,
+                            ty: ty.clone(),
                             optional: *optional,
+                            unique: false,
+                            default: None,
+                            check: None,
+                            references: None,
                             values: Vec::new(),
                         });
+                        expressions.push(Expression::Identifier(identifier.clone()));
                     }
                 }
             }
         }
 
-        for i in 0..self.height() {
-            let row = self.row(i).unwrap(); // 0..self.height() must exist
-            if let Some(expression) = filter.clone() {
-                let Value::Bool(bool) = Self::evaluate(expression, &row).map_err(OneOf::broaden)?
-                else {
-                    return Err(OneOf::new(ExpectedBoolError));
-                };
+        let expressions = expressions
+            .into_iter()
+            .map(|expression| Self::queried(expression, database))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let indices = match filter {
+            Some(filter) => self.find(filter, database)?,
+            None => (0..self.height()).collect(),
+        };
+
+        let rows = indices
+            .into_iter()
+            .map(|index| self.row(index).unwrap()) // Found indices must exist.
+            .collect::<Vec<_>>();
 
-                if !bool {
-                    continue;
+        let aggregated = expressions.iter().any(|expression| {
+            matches!(
+                expression,
+                Expression::Call { identifier, .. } if function::is_aggregate(identifier)
+            )
+        });
+
+        let unnested = expressions
+            .iter()
+            .map(|expression| match expression {
+                Expression::Call {
+                    identifier,
+                    arguments,
+                } if function::is_unnest(identifier) => match arguments.as_slice() {
+                    [argument] => Ok(Some(argument)),
+                    _ => Err(OneOf::new(FunctionCallError::IncorrectArgumentCount {
+                        function: identifier.clone(),
+                        count: arguments.len(),
+                    })),
+                },
+                _ => Ok(None),
+            })
+            .collect::<Result<Vec<_>, EvaluateError>>()?;
+
+        for ((Column { values, .. }, expression), unnested) in
+            columns.iter_mut().zip(&expressions).zip(&unnested)
+        {
+            if !aggregated {
+                // Lists to unnest are expanded once all the columns are evaluated.
+                let expression = unnested.unwrap_or(expression);
+                for row in &rows {
+                    let value = Self::evaluate(expression.clone(), row, database)?;
+                    if unnested.is_some() && !matches!(value, Value::List(_) | Value::Nil) {
+                        return Err(OneOf::new(FunctionCallError::IncorrectArgumentTy {
+                            function: "unnest".to_owned(),
+                            index: 0,
+                            ty: value.ty(),
+                        }));
+                    }
+
+                    values.push(value);
                 }
+
+                continue;
             }
 
-            for Column {
-                identifier, values, ..
-            } in &mut columns
-            {
-                let Some(value) = row.get(identifier) else {
-                    unreachable!();
-                };
+            let Expression::Call {
+                identifier,
+                arguments,
+            } = expression
+            else {
+                return Err(OneOf::new(FunctionCallError::MixedAggregate));
+            };
+
+            if !function::is_aggregate(identifier) {
+                return Err(OneOf::new(FunctionCallError::MixedAggregate));
+            }
+
+            let [argument] = arguments.as_slice() else {
+                return Err(OneOf::new(FunctionCallError::IncorrectArgumentCount {
+                    function: identifier.clone(),
+                    count: arguments.len(),
+                }));
+            };
+
+            let arguments = rows
+                .iter()
+                .map(|row| Self::evaluate(argument.clone(), row, database))
+                .collect::<Result<Vec<_>, _>>()?;
+
+            values.push(function::aggregate(identifier, arguments).map_err(OneOf::broaden)?);
+        }
+
+        if unnested.iter().any(Option::is_some) {
+            Self::unnest(&mut columns, &unnested);
+        }
+
+        for (column, expression) in columns.iter_mut().zip(&expressions) {
+            if let Expression::Identifier(_) = expression {
+                continue;
+            }
+
+            column.optional = column.values.iter().any(|value| value.ty() == Ty::Nil);
+            if let Some(value) = column.values.iter().find(|value| value.ty() != Ty::Nil) {
+                column.ty = value.ty();
+            }
+        }
+
+        let mut table = Table {
+            columns,
+            key: Key::None,
+            sequence: 0,
+        };
+        if distinct {
+            table.remove_duplicates();
+        }
+
+        Ok(table)
+    }
 
-                values.push(value.clone());
+    /// Expands every row into a row for each element of the lists in the `unnested` columns,
+    /// repeating the values of the other columns. Rows get as many copies as their longest list,
+    /// shorter lists are filled with nil.
+    fn unnest(columns: &mut [Column], unnested: &[Option<&Expression>]) {
+        let height = columns.first().map_or(0, |column| column.values.len());
+        let mut expanded = vec![Vec::new(); columns.len()];
+        for index in 0..height {
+            let count = columns
+                .iter()
+                .zip(unnested)
+                .filter_map(
+                    |(column, unnested)| match (&column.values[index], unnested) {
+                        (Value::List(list), Some(_)) => Some(list.len()),
+                        _ => None,
+                    },
+                )
+                .max()
+                .unwrap_or(0);
+
+            for element in 0..count {
+                for ((column, unnested), values) in columns.iter().zip(unnested).zip(&mut expanded)
+                {
+                    values.push(match (&column.values[index], unnested) {
+                        (Value::List(list), Some(_)) => {
+                            list.get(element).cloned().unwrap_or(Value::Nil)
+                        }
+                        (_, Some(_)) => Value::Nil,
+                        (value, None) => value.clone(),
+                    });
+                }
             }
         }
 
-        Ok(Table { columns })
+        for (column, values) in columns.iter_mut().zip(expanded) {
+            column.values = values;
+        }
+    }
+
+    /// Keeps only the first of every set of equal rows.
+    fn remove_duplicates(&mut self) {
+        let mut rows = HashSet::new();
+        let unique = (0..self.height())
+            .map(|i| rows.insert(self.row_values(i)))
+            .collect::<Vec<_>>();
+
+        self.retain_rows(&unique);
     }
 
-    pub fn insert(&mut self, mut insertion: HashMap<String, Value>) -> Result<(), InsertError> {
-        // Insertion validation.
-        // ---------------------@
-        if insertion.contains_key(ID_IDENTIFIER) {
-            return Err(OneOf::new(IdInsertError));
+    /// Keeps only the rows for which `retain` is `true`.
+    fn retain_rows(&mut self, retain: &[bool]) {
+        for Column { values, .. } in &mut self.columns {
+            let mut retain = retain.iter();
+            values.retain(|_| *retain.next().unwrap());
         }
+    }
 
-        let last_id = {
-            if self.height() == 0 {
-                0
-            } else {
-                let Value::Int(id) = self
-                    .column(ID_IDENTIFIER)
-                    .expect("column Id does not exist?")
-                    .values
-                    .last()
-                    .expect("?")
-                else {
-                    panic!("Id is not an int?")
-                };
+    /// # Panics
+    /// if `index >= self.height()`
+    fn row_values(&self, index: usize) -> Vec<Value> {
+        self.columns
+            .iter()
+            .map(|Column { values, .. }| values[index].clone())
+            .collect()
+    }
+
+    /// Combines rows of two tables selecting columns of the same types, taking the column names
+    /// from `self`. Apart from "union all" the result has no duplicate rows.
+    pub fn combined(
+        mut self,
+        operation: SetOperation,
+        rhs: Table,
+    ) -> Result<Table, IncompatibleQueriesError> {
+        if self.width() != rhs.width() {
+            return Err(IncompatibleQueriesError::ColumnCount);
+        }
 
-                *id as i32 + 1
+        for (index, (lhs, rhs)) in self.columns.iter_mut().zip(&rhs.columns).enumerate() {
+            // Columns with only nil values have no type.
+            match (lhs.ty.clone(), rhs.ty.clone()) {
+                (lhs_ty, rhs_ty) if lhs_ty == rhs_ty => {}
+                (Ty::Nil, ty) => lhs.ty = ty,
+                (
+                    Ty::Decimal {
+                        precision: lhs_precision,
+                        scale: lhs_scale,
+                    },
+                    Ty::Decimal {
+                        precision: rhs_precision,
+                        scale: rhs_scale,
+                    },
+                ) => {
+                    let scale = lhs_scale.max(rhs_scale);
+                    let digits = (lhs_precision - lhs_scale).max(rhs_precision - rhs_scale);
+                    lhs.ty = Ty::Decimal {
+                        precision: (digits + scale).min(MAX_PRECISION),
+                        scale,
+                    };
+                }
+                (_, Ty::Nil) => {}
+                (lhs, rhs) => return Err(IncompatibleQueriesError::ColumnTy { index, lhs, rhs }),
             }
-        };
-        insertion.insert(ID_IDENTIFIER.to_owned(), Value::Int(last_id));
+
+            lhs.optional |= rhs.optional;
+        }
+
+        match operation {
+            SetOperation::Union | SetOperation::UnionAll => {
+                for (lhs, rhs) in self.columns.iter_mut().zip(rhs.columns) {
+                    lhs.values.extend(rhs.values);
+                }
+
+                if let SetOperation::Union = operation {
+                    self.remove_duplicates();
+                }
+            }
+            SetOperation::Intersect | SetOperation::Except => {
+                let rows = (0..rhs.height())
+                    .map(|i| rhs.row_values(i))
+                    .collect::<HashSet<_>>();
+
+                let intersect = matches!(operation, SetOperation::Intersect);
+                let retain = (0..self.height())
+                    .map(|i| rows.contains(&self.row_values(i)) == intersect)
+                    .collect::<Vec<_>>();
+
+                self.retain_rows(&retain);
+                self.remove_duplicates();
+            }
+        }
+
+        Ok(self)
+    }
+
+    /// Evaluates the values of an insertion, which cannot refer to any column.
+    fn evaluated(
+        insertion: HashMap<String, Expression>,
+        database: &Database,
+    ) -> Result<HashMap<String, Value>, EvaluateError> {
+        let row = HashMap::new();
+        insertion
+            .into_iter()
+            .map(|(identifier, expression)| {
+                Ok((identifier, Self::evaluate(expression, &row, database)?))
+            })
+            .collect()
+    }
+
+    /// Validates an insertion against the columns and their constraints, returning the complete
+    /// row to insert.
+    pub fn validated(
+        &self,
+        mut insertion: HashMap<String, Value>,
+        database: &Database,
+    ) -> Result<HashMap<String, Value>, InsertError> {
+        if self.key == Key::Id {
+            if insertion.contains_key(ID_IDENTIFIER) {
+                return Err(OneOf::new(IdInsertError));
+            }
+
+            insertion.insert(ID_IDENTIFIER.to_owned(), Value::Int(self.sequence));
+        }
+
+        for column in &self.columns {
+            if let Some(default) = &column.default {
+                if !insertion.contains_key(&column.identifier) {
+                    insertion.insert(column.identifier.clone(), default.clone());
+                }
+            }
+        }
 
         let mut columns = self
             .columns
@@ -403,13 +1183,13 @@ impl Table {
                      identifier,
                      ty,
                      optional,
-                     values,
-                 }| (identifier, (ty, optional, values)),
+                     ..
+                 }| (identifier, (ty, optional)),
             )
             .collect::<HashMap<_, _>>();
 
-        for (identifier, value) in &insertion {
-            let Some((ty, optional, _)) = columns.remove(identifier) else {
+        for (identifier, value) in &mut insertion {
+            let Some((ty, optional)) = columns.remove(identifier) else {
                 return Err(OneOf::new(NoSuchColumnError(identifier.clone())));
             };
 
@@ -417,80 +1197,419 @@ impl Table {
                 continue;
             }
 
-            if value.ty() != *ty {
+            let Some(conformed) = database.stored(std::mem::replace(value, Value::Nil), ty) else {
                 return Err(OneOf::new(InsertionError::IncorrectTy {
                     column: identifier.clone(),
-                    ty: *ty,
+                    ty: ty.clone(),
                 }));
-            }
-        }
+            };
+
+            *value = conformed;
+        }
 
-        for (identifier, (_, optional, _)) in columns {
+        for (identifier, (_, optional)) in columns {
             if !*optional {
                 return Err(OneOf::new(InsertionError::NonOptionalColumn {
                     column: identifier.clone(),
                 }));
             }
+
+            insertion.insert(identifier.clone(), Value::Nil);
         }
-        // ---------------------@
 
-        let mut columns = self
-            .columns
-            .iter_mut()
-            .map(
-                |Column {
-                     identifier, values, ..
-                 }| (identifier, values),
-            )
-            .collect::<HashMap<_, _>>();
+        if let Key::Primary(key) = &self.key {
+            let key = key
+                .iter()
+                .map(|column| insertion[column].clone())
+                .collect::<Vec<_>>();
 
-        for (identifier, value) in insertion {
-            // This should get validated before. That's why here it should be unreachable.
-            let Some(values) = columns.remove(&identifier) else {
-                unreachable!();
+            if self.find_key(&key).is_some() {
+                return Err(OneOf::new(InsertionError::DuplicateKey));
+            }
+        }
+
+        for Column {
+            identifier,
+            unique,
+            check,
+            references,
+            values,
+            ..
+        } in &self.columns
+        {
+            let value = &insertion[identifier];
+            if *unique && *value != Value::Nil && values.contains(value) {
+                return Err(OneOf::new(InsertionError::NotUnique {
+                    column: identifier.clone(),
+                }));
+            }
+
+            if let Some(Reference { table, .. }) = references {
+                let exists = *value == Value::Nil
+                    || database
+                        .tables
+                        .get(table)
+                        .is_some_and(|table| table.has_key(value));
+
+                if !exists {
+                    return Err(OneOf::new(ReferenceError::NoSuchRow {
+                        column: identifier.clone(),
+                        table: table.clone(),
+                    }));
+                }
+            }
+
+            let Some(check) = check else {
+                continue;
             };
 
-            values.push(value);
+            // Like a "where", but an unknown result passes the check.
+            match Self::evaluate(check.clone(), &insertion, database).map_err(OneOf::new)? {
+                Value::Bool(true) | Value::Nil => {}
+                Value::Bool(false) => {
+                    return Err(OneOf::new(InsertionError::CheckViolation {
+                        column: identifier.clone(),
+                    }))
+                }
+                _ => return Err(OneOf::new(EvaluateError::new(ExpectedBoolError))),
+            }
         }
 
-        for values in columns.values_mut() {
-            values.push(Value::Nil);
-        }
+        Ok(insertion)
+    }
 
+    /// Validates an insertion with [`Table::validated`] and inserts it, references are looked up
+    /// in `database`.
+    pub fn insert(
+        &mut self,
+        insertion: HashMap<String, Value>,
+        database: &Database,
+    ) -> Result<(), InsertError> {
+        let row = self.validated(insertion, database)?;
+        self.push(row);
         Ok(())
     }
 
+    /// Inserts a row returned from [`Table::validated`].
+    fn push(&mut self, mut row: HashMap<String, Value>) {
+        if self.key == Key::Id {
+            self.sequence += 1;
+        }
+
+        for Column {
+            identifier, values, ..
+        } in &mut self.columns
+        {
+            values.push(
+                row.remove(identifier)
+                    .expect("validated rows have every column"),
+            );
+        }
+    }
+
+    /// Identifiers of the columns making up the key.
+    fn key(&self) -> Vec<&str> {
+        match &self.key {
+            Key::Id => vec![ID_IDENTIFIER],
+            Key::Primary(key) => key.iter().map(String::as_str).collect(),
+            Key::None => Vec::new(),
+        }
+    }
+
+    /// The column of a key made of a single column, which other tables can reference.
+    fn key_column(&self) -> Option<&Column> {
+        match self.key()[..] {
+            [column] => self.column(column),
+            _ => None,
+        }
+    }
+
+    /// Returns the index of the row whose key columns hold the values of `key`.
+    pub fn find_key(&self, key: &[Value]) -> Option<usize> {
+        let columns = self
+            .key()
+            .into_iter()
+            .map(|column| self.column(column).expect("key columns exist"))
+            .collect::<Vec<_>>();
+
+        if columns.is_empty() || columns.len() != key.len() {
+            return None;
+        }
+
+        (0..self.height()).find(|index| {
+            columns
+                .iter()
+                .zip(key)
+                .all(|(column, value)| column.values[*index] == *value)
+        })
+    }
+
+    /// The keys of the rows at `indices`, a table without a single column key cannot be
+    /// referenced so it has none.
+    fn keys(&self, indices: &[usize]) -> HashSet<Value> {
+        let Some(Column { values, .. }) = self.key_column() else {
+            return HashSet::new();
+        };
+
+        indices.iter().map(|index| values[*index].clone()).collect()
+    }
+
+    fn has_key(&self, key: &Value) -> bool {
+        self.key_column()
+            .is_some_and(|column| column.values.contains(key))
+    }
+
+    /// Returns the indices of rows whose `column` holds one of the `keys`.
+    fn referencing(&self, column: &str, keys: &HashSet<Value>) -> Vec<usize> {
+        let values = &self.column(column).expect("?").values;
+        (0..values.len())
+            .filter(|index| keys.contains(&values[*index]))
+            .collect()
+    }
+
+    /// Returns the indices of rows matching the `filter` in ascending order.
+    pub fn find(
+        &self,
+        filter: Expression,
+        database: &Database,
+    ) -> Result<Vec<usize>, EvaluateError> {
+        let filter = Self::queried(filter, database)?;
+        let mut indices = Vec::new();
+        for i in 0..self.height() {
+            let row = self.row(i).expect("?");
+            if Self::test(filter.clone(), &row, database)? {
+                indices.push(i);
+            }
+        }
+
+        Ok(indices)
+    }
+
+    /// # Panics
+    /// if `indices` are not in ascending order or any of them is out of bounds.
+    pub fn remove_rows(&mut self, indices: &[usize]) {
+        for (i, index) in indices.iter().enumerate() {
+            // Need to offset indices after every removal.
+            self.remove_row(index - i);
+        }
+    }
+
+    /// Removes the rows matching the `filter`, returning how many were removed. Rows of other
+    /// tables referencing them are left as is, [`Database::run_command`] enforces `on remove`.
     pub fn remove(
         &mut self,
+        filter: Expression,
+        database: &Database,
+    ) -> Result<usize, EvaluateError> {
+        let indices = self.find(filter, database)?;
+        self.remove_rows(&indices);
+        Ok(indices.len())
+    }
+
+    /// Evaluates a filter for a row, an unknown (nil) result counts as `false`.
+    fn test(
         expression: Expression,
-    ) -> Result<usize, OneOf<(ExpectedBoolError, CannotEvaluateError, NoSuchColumnError)>> {
-        let mut remove_indices = Vec::new();
-        for i in 0..self.height() {
-            let row = self.row(i).expect("?");
-            let Value::Bool(bool) =
-                Self::evaluate(expression.clone(), &row).map_err(OneOf::broaden)?
-            else {
-                return Err(OneOf::new(ExpectedBoolError));
+        row: &HashMap<String, Value>,
+        database: &Database,
+    ) -> Result<bool, EvaluateError> {
+        match Self::evaluate(expression, row, database)? {
+            Value::Bool(bool) => Ok(bool),
+            Value::Nil => Ok(false),
+            _ => Err(OneOf::new(ExpectedBoolError)),
+        }
+    }
+
+    /// Matches `str` against a "like" pattern, where `%` matches any sequence of characters
+    /// and `_` matches any single character. A `\` makes the character after it match itself.
+    fn like(str: &str, pattern: &str) -> bool {
+        let str = str.chars().collect::<Vec<_>>();
+        let pattern = pattern.chars().collect::<Vec<_>>();
+
+        let (mut i, mut j) = (0, 0);
+        // Position to go back to when a match after the last `%` fails.
+        let mut backtrack = None;
+        while i < str.len() {
+            match pattern.get(j) {
+                Some('%') => {
+                    backtrack = Some((i, j));
+                    j += 1;
+                }
+                Some('\\') if pattern.get(j + 1) == Some(&str[i]) => {
+                    i += 1;
+                    j += 2;
+                }
+                Some(char) if *char != '\\' && (*char == '_' || *char == str[i]) => {
+                    i += 1;
+                    j += 1;
+                }
+                _ => {
+                    let Some((last_i, last_j)) = backtrack else {
+                        return false;
+                    };
+
+                    backtrack = Some((last_i + 1, last_j));
+                    i = last_i + 1;
+                    j = last_j + 1;
+                }
+            }
+        }
+
+        pattern[j..].iter().all(|char| *char == '%')
+    }
+
+    fn negated(value: Value, negated: bool) -> Value {
+        match value {
+            Value::Bool(bool) if negated => Value::Bool(!bool),
+            value => value,
+        }
+    }
+
+    /// Enum values as the name of their variant, other values as they are.
+    fn variant_name(value: Value) -> Value {
+        match value {
+            Value::Enum(..) => Value::Str(value.variant().to_owned()),
+            value => value,
+        }
+    }
+
+    /// The single value selected by a subquery, nil if it selects no rows.
+    fn query_value(command: Command, database: &Database) -> Result<Value, EvaluateError> {
+        let table = database.query(command)?;
+        match (table.width(), table.height()) {
+            (1, 0) => Ok(Value::Nil),
+            (1, 1) => Ok(table.columns[0].values[0].clone()),
+            _ => Err(OneOf::new(QueryError::new(ExpectedValueError))),
+        }
+    }
+
+    /// The values of the single column selected by a subquery.
+    fn query_values(command: Command, database: &Database) -> Result<Vec<Value>, EvaluateError> {
+        let mut table = database.query(command)?;
+        if table.width() != 1 {
+            return Err(OneOf::new(QueryError::new(ExpectedValueError)));
+        }
+
+        Ok(table.columns.remove(0).values)
+    }
+
+    /// Replaces the subqueries of an expression with their values. They do not depend on the
+    /// row, so they only need to run once for all the rows.
+    fn queried(expression: Expression, database: &Database) -> Result<Expression, EvaluateError> {
+        let queried =
+            |expression: Box<Expression>| Self::queried(*expression, database).map(Box::new);
+        Ok(match expression {
+            Expression::Query(command) => Expression::Value(Self::query_value(*command, database)?),
+            Expression::In {
+                expression,
+                list,
+                negated,
+            } => {
+                let mut items = Vec::new();
+                for item in list {
+                    match item {
+                        // Subqueries expand into all of their values.
+                        Expression::Query(command) => items.extend(
+                            Self::query_values(*command, database)?
+                                .into_iter()
+                                .map(Expression::Value),
+                        ),
+                        item => items.push(Self::queried(item, database)?),
+                    }
+                }
+
+                Expression::In {
+                    expression: queried(expression)?,
+                    list: items,
+                    negated,
+                }
+            }
+            Expression::Enclosed(expression) => Expression::Enclosed(queried(expression)?),
+            Expression::Cast { expression, ty } => Expression::Cast {
+                expression: queried(expression)?,
+                ty,
+            },
+            Expression::Call {
+                identifier,
+                arguments,
+            } => Expression::Call {
+                identifier,
+                arguments: arguments
+                    .into_iter()
+                    .map(|argument| Self::queried(argument, database))
+                    .collect::<Result<_, _>>()?,
+            },
+            Expression::Operation { lhs, operator, rhs } => Expression::Operation {
+                lhs: queried(lhs)?,
+                operator,
+                rhs: queried(rhs)?,
+            },
+            Expression::Between {
+                expression,
+                low,
+                high,
+                negated,
+            } => Expression::Between {
+                expression: queried(expression)?,
+                low: queried(low)?,
+                high: queried(high)?,
+                negated,
+            },
+            expression @ (Expression::Value(_) | Expression::Identifier(_)) => expression,
+        })
+    }
+
+    /// Whether any of `items` equals `value` by "==". Stays unknown if there is no match, but
+    /// some comparison was unknown.
+    fn contained(
+        value: Value,
+        items: Vec<Value>,
+        row: &HashMap<String, Value>,
+        database: &Database,
+    ) -> Result<Value, EvaluateError> {
+        let mut result = Value::Bool(false);
+        for item in items {
+            let equal = Expression::Operation {
+                lhs: Box::new(Expression::Value(value.clone())),
+                operator: Operator::Eq,
+                rhs: Box::new(Expression::Value(item)),
             };
 
-            if bool {
-                remove_indices.push(i);
+            match Self::evaluate(equal, row, database)? {
+                Value::Bool(true) => return Ok(Value::Bool(true)),
+                Value::Nil => result = Value::Nil,
+                _ => {}
             }
         }
 
-        let count = remove_indices.len();
-        for (i, index) in remove_indices.into_iter().enumerate() {
-            // Need to offset indices after every removal.
-            self.remove_row(index - i);
+        Ok(result)
+    }
+
+    fn checked<T>(int: Option<T>) -> Result<T, EvaluateError> {
+        int.ok_or_else(|| OneOf::new(IntegerOverflowError))
+    }
+
+    fn division_by_zero<T>() -> Result<T, EvaluateError> {
+        Err(OneOf::new(ArithmeticError::new(DivisionByZeroError)))
+    }
+
+    /// A date or timestamp resulting from arithmetic, `None` if it is out of range.
+    fn dated(value: Option<Value>) -> Result<Value, EvaluateError> {
+        value.ok_or_else(|| OneOf::new(ArithmeticError::new(DateRangeError)))
+    }
+
+    fn divided(lhs: Decimal, rhs: Decimal) -> Result<Value, EvaluateError> {
+        if rhs == Decimal::from(0) {
+            return Self::division_by_zero();
         }
 
-        Ok(count)
+        Ok(Value::Decimal(Self::checked(lhs.checked_div(rhs))?))
     }
 
     fn evaluate(
         expression: Expression,
         row: &HashMap<String, Value>,
-    ) -> Result<Value, OneOf<(CannotEvaluateError, NoSuchColumnError)>> {
+        database: &Database,
+    ) -> Result<Value, EvaluateError> {
         match expression {
             Expression::Value(value) => Ok(value),
             Expression::Identifier(identifer) => {
@@ -500,48 +1619,413 @@ impl Table {
 
                 Ok(value.clone())
             }
-            Expression::Enclosed(expression) => Self::evaluate(*expression, row),
+            Expression::Enclosed(expression) => Self::evaluate(*expression, row, database),
+            Expression::Cast { expression, ty } => {
+                let value = Self::evaluate(*expression, row, database)?;
+                let ty = database.resolved(ty).map_err(|err| match err {
+                    TypeError::NoSuchType(identifier) => {
+                        OneOf::new(CastError::NoSuchType(identifier))
+                    }
+                    _ => unreachable!("resolving only fails for missing types"),
+                })?;
+
+                cast::cast(value, &ty).map_err(OneOf::new)
+            }
+            Expression::Call {
+                identifier,
+                arguments,
+            } => {
+                let arguments = arguments
+                    .into_iter()
+                    .map(|argument| Self::evaluate(argument, row, database))
+                    .collect::<Result<Vec<_>, _>>()?;
+
+                // The built-in "contains" compares elements like "in" does.
+                if let ("contains", [Value::List(_), value]) =
+                    (identifier.as_str(), arguments.as_slice())
+                {
+                    if *value != Value::Nil && !database.functions.is_registered(&identifier) {
+                        let mut arguments = arguments.into_iter();
+                        let (Some(Value::List(items)), Some(value)) =
+                            (arguments.next(), arguments.next())
+                        else {
+                            unreachable!();
+                        };
+
+                        return Self::contained(value, items, row, database);
+                    }
+                }
+
+                database
+                    .functions
+                    .call(&identifier, arguments)
+                    .map_err(OneOf::broaden)
+            }
+            Expression::Query(command) => Self::query_value(*command, database),
+            Expression::In {
+                expression,
+                list,
+                negated,
+            } => {
+                let value = Self::evaluate(*expression, row, database)?;
+
+                let mut items = Vec::new();
+                for item in list {
+                    match item {
+                        // Subqueries expand into all of their values.
+                        Expression::Query(command) => {
+                            items.append(&mut Self::query_values(*command, database)?)
+                        }
+                        item => items.push(Self::evaluate(item, row, database)?),
+                    }
+                }
+
+                let result = Self::contained(value, items, row, database)?;
+                Ok(Self::negated(result, negated))
+            }
+            Expression::Between {
+                expression,
+                low,
+                high,
+                negated,
+            } => {
+                let value = Expression::Value(Self::evaluate(*expression, row, database)?);
+                let between = Expression::Operation {
+                    lhs: Box::new(Expression::Operation {
+                        lhs: low,
+                        operator: Operator::LessEq,
+                        rhs: Box::new(value.clone()),
+                    }),
+                    operator: Operator::And,
+                    rhs: Box::new(Expression::Operation {
+                        lhs: Box::new(value),
+                        operator: Operator::LessEq,
+                        rhs: high,
+                    }),
+                };
+
+                Ok(Self::negated(
+                    Self::evaluate(between, row, database)?,
+                    negated,
+                ))
+            }
             Expression::Operation { lhs, operator, rhs } => {
+                let lhs = Self::evaluate(*lhs, row, database)?;
+                let rhs = Self::evaluate(*rhs, row, database)?;
+                match (operator, &lhs, &rhs) {
+                    // "&&" and "||" follow Kleene logic, a known operand can still decide
+                    // the result when the other one is unknown.
+                    (Operator::And, Value::Bool(false), Value::Bool(_) | Value::Nil)
+                    | (Operator::And, Value::Nil, Value::Bool(false)) => {
+                        return Ok(Value::Bool(false))
+                    }
+                    (Operator::Or, Value::Bool(true), Value::Bool(_) | Value::Nil)
+                    | (Operator::Or, Value::Nil, Value::Bool(true)) => {
+                        return Ok(Value::Bool(true))
+                    }
+                    (Operator::And | Operator::Or, Value::Bool(_) | Value::Nil, Value::Nil)
+                    | (Operator::And | Operator::Or, Value::Nil, Value::Bool(_)) => {
+                        return Ok(Value::Nil)
+                    }
+                    (Operator::And | Operator::Or, _, _) => {}
+                    // Any other operation involving nil is unknown.
+                    (_, Value::Nil, _) | (_, _, Value::Nil) => return Ok(Value::Nil),
+                    _ => {}
+                }
+
+                // Enums compare in the order their variants were declared, and by the name of
+                // the variant against strings.
+                let (lhs, rhs) = match (operator, lhs, rhs) {
+                    (
+                        Operator::Eq
+                        | Operator::Less
+                        | Operator::LessEq
+                        | Operator::More
+                        | Operator::MoreEq,
+                        Value::Enum(lhs_ty, lhs),
+                        Value::Enum(rhs_ty, rhs),
+                    ) if lhs_ty == rhs_ty => (Value::Int(lhs.into()), Value::Int(rhs.into())),
+                    (Operator::Eq, lhs, rhs) => (Self::variant_name(lhs), Self::variant_name(rhs)),
+                    (_, lhs, rhs) => (lhs, rhs),
+                };
+
                 crate::operator_map! {
-                    Self::evaluate(*lhs, row)?,
+                    lhs,
                     operator,
-                    Self::evaluate(*rhs, row)?,
+                    rhs,
                     Add {
-                        Int(lhs), Int(rhs) => Value::Int(lhs + rhs)
+                        Int(lhs), Int(rhs) => Value::Int(Self::checked(lhs.checked_add(rhs))?)
                         Float(lhs), Float(rhs) => Value::Float(lhs + rhs)
+                        Int(lhs), Float(rhs) => Value::Float(lhs as f64 + rhs)
+                        Float(lhs), Int(rhs) => Value::Float(lhs + rhs as f64)
+                        Decimal(lhs), Float(rhs) => Value::Float(lhs.to_float() + rhs)
+                        Float(lhs), Decimal(rhs) => Value::Float(lhs + rhs.to_float())
+                        Decimal(lhs), Decimal(rhs) => {
+                            Value::Decimal(Self::checked(lhs.checked_add(rhs))?)
+                        }
+                        Decimal(lhs), Int(rhs) => {
+                            Value::Decimal(Self::checked(lhs.checked_add(Decimal::from(rhs)))?)
+                        }
+                        Int(lhs), Decimal(rhs) => {
+                            Value::Decimal(Self::checked(Decimal::from(lhs).checked_add(rhs))?)
+                        }
+                        Str(lhs), Str(rhs) => Value::Str(lhs + &rhs)
+                        Date(lhs), Interval(rhs) => Self::dated(datetime::add_to_date(lhs, rhs))?
+                        Interval(lhs), Date(rhs) => Self::dated(datetime::add_to_date(rhs, lhs))?
+                        Timestamp(lhs), Interval(rhs) => {
+                            Self::dated(lhs.checked_add(rhs).and_then(datetime::timestamp))?
+                        }
+                        Interval(lhs), Timestamp(rhs) => {
+                            Self::dated(rhs.checked_add(lhs).and_then(datetime::timestamp))?
+                        }
+                        Time(lhs), Interval(rhs) => {
+                            let time = lhs.checked_add(rhs % datetime::SECONDS_PER_DAY);
+                            Value::Time(Self::checked(time)?.rem_euclid(datetime::SECONDS_PER_DAY))
+                        }
+                        Interval(lhs), Interval(rhs) => {
+                            Value::Interval(Self::checked(lhs.checked_add(rhs))?)
+                        }
                     }
                     Sub {
-                        Int(lhs), Int(rhs) => Value::Int(lhs - rhs)
+                        Int(lhs), Int(rhs) => Value::Int(Self::checked(lhs.checked_sub(rhs))?)
                         Float(lhs), Float(rhs) => Value::Float(lhs - rhs)
+                        Int(lhs), Float(rhs) => Value::Float(lhs as f64 - rhs)
+                        Float(lhs), Int(rhs) => Value::Float(lhs - rhs as f64)
+                        Decimal(lhs), Float(rhs) => Value::Float(lhs.to_float() - rhs)
+                        Float(lhs), Decimal(rhs) => Value::Float(lhs - rhs.to_float())
+                        Decimal(lhs), Decimal(rhs) => {
+                            Value::Decimal(Self::checked(lhs.checked_sub(rhs))?)
+                        }
+                        Decimal(lhs), Int(rhs) => {
+                            Value::Decimal(Self::checked(lhs.checked_sub(Decimal::from(rhs)))?)
+                        }
+                        Int(lhs), Decimal(rhs) => {
+                            Value::Decimal(Self::checked(Decimal::from(lhs).checked_sub(rhs))?)
+                        }
+                        Date(lhs), Interval(rhs) => {
+                            let rhs = Self::checked(rhs.checked_neg())?;
+                            Self::dated(datetime::add_to_date(lhs, rhs))?
+                        }
+                        Timestamp(lhs), Interval(rhs) => {
+                            Self::dated(lhs.checked_sub(rhs).and_then(datetime::timestamp))?
+                        }
+                        Time(lhs), Interval(rhs) => {
+                            let time = lhs.checked_sub(rhs % datetime::SECONDS_PER_DAY);
+                            Value::Time(Self::checked(time)?.rem_euclid(datetime::SECONDS_PER_DAY))
+                        }
+                        Date(lhs), Date(rhs) => {
+                            let days = Self::checked(lhs.checked_sub(rhs))?;
+                            let seconds = days.checked_mul(datetime::SECONDS_PER_DAY);
+                            Value::Interval(Self::checked(seconds)?)
+                        }
+                        Timestamp(lhs), Timestamp(rhs) => {
+                            Value::Interval(Self::checked(lhs.checked_sub(rhs))?)
+                        }
+                        Time(lhs), Time(rhs) => {
+                            Value::Interval(Self::checked(lhs.checked_sub(rhs))?)
+                        }
+                        Interval(lhs), Interval(rhs) => {
+                            Value::Interval(Self::checked(lhs.checked_sub(rhs))?)
+                        }
                     }
                     Mul {
-                        Int(lhs), Int(rhs) => Value::Int(lhs * rhs)
+                        Int(lhs), Int(rhs) => Value::Int(Self::checked(lhs.checked_mul(rhs))?)
                         Float(lhs), Float(rhs) => Value::Float(lhs * rhs)
+                        Int(lhs), Float(rhs) => Value::Float(lhs as f64 * rhs)
+                        Float(lhs), Int(rhs) => Value::Float(lhs * rhs as f64)
+                        Decimal(lhs), Float(rhs) => Value::Float(lhs.to_float() * rhs)
+                        Float(lhs), Decimal(rhs) => Value::Float(lhs * rhs.to_float())
+                        Decimal(lhs), Decimal(rhs) => {
+                            Value::Decimal(Self::checked(lhs.checked_mul(rhs))?)
+                        }
+                        Decimal(lhs), Int(rhs) => {
+                            Value::Decimal(Self::checked(lhs.checked_mul(Decimal::from(rhs)))?)
+                        }
+                        Int(lhs), Decimal(rhs) => {
+                            Value::Decimal(Self::checked(Decimal::from(lhs).checked_mul(rhs))?)
+                        }
+                        Interval(lhs), Int(rhs) => {
+                            Value::Interval(Self::checked(lhs.checked_mul(rhs))?)
+                        }
+                        Int(lhs), Interval(rhs) => {
+                            Value::Interval(Self::checked(lhs.checked_mul(rhs))?)
+                        }
                     }
                     Div {
-                        Int(lhs), Int(rhs) => Value::Int(lhs / rhs)
-                        Float(lhs), Float(rhs) => Value::Float(lhs / rhs)
+                        Int(lhs), Int(rhs) => {
+                            if rhs == 0 {
+                                return Self::division_by_zero();
+                            }
+
+                            Value::Int(Self::checked(lhs.checked_div(rhs))?)
+                        }
+                        Float(lhs), Float(rhs) => {
+                            if rhs == 0.0 {
+                                return Self::division_by_zero();
+                            }
+
+                            Value::Float(lhs / rhs)
+                        }
+                        Int(lhs), Float(rhs) => {
+                            if rhs == 0.0 {
+                                return Self::division_by_zero();
+                            }
+
+                            Value::Float(lhs as f64 / rhs)
+                        }
+                        Float(lhs), Int(rhs) => {
+                            if rhs == 0 {
+                                return Self::division_by_zero();
+                            }
+
+                            Value::Float(lhs / rhs as f64)
+                        }
+                        Decimal(lhs), Float(rhs) => {
+                            if rhs == 0.0 {
+                                return Self::division_by_zero();
+                            }
+
+                            Value::Float(lhs.to_float() / rhs)
+                        }
+                        Float(lhs), Decimal(rhs) => {
+                            let rhs = rhs.to_float();
+                            if rhs == 0.0 {
+                                return Self::division_by_zero();
+                            }
+
+                            Value::Float(lhs / rhs)
+                        }
+                        Decimal(lhs), Decimal(rhs) => Self::divided(lhs, rhs)?
+                        Decimal(lhs), Int(rhs) => Self::divided(lhs, Decimal::from(rhs))?
+                        Int(lhs), Decimal(rhs) => Self::divided(Decimal::from(lhs), rhs)?
+                        Interval(lhs), Int(rhs) => {
+                            if rhs == 0 {
+                                return Self::division_by_zero();
+                            }
+
+                            Value::Interval(Self::checked(lhs.checked_div(rhs))?)
+                        }
                     }
                     Eq {
                         Int(lhs), Int(rhs) => Value::Bool(lhs == rhs)
                         Float(lhs), Float(rhs) => Value::Bool(lhs == rhs)
+                        Int(lhs), Float(rhs) => Value::Bool((lhs as f64) == rhs)
+                        Float(lhs), Int(rhs) => Value::Bool(lhs == rhs as f64)
+                        Decimal(lhs), Float(rhs) => Value::Bool(lhs.to_float() == rhs)
+                        Float(lhs), Decimal(rhs) => Value::Bool(lhs == rhs.to_float())
                         Str(lhs), Str(rhs) => Value::Bool(lhs == rhs)
+                        Bytes(lhs), Bytes(rhs) => Value::Bool(lhs == rhs)
+                        List(lhs), List(rhs) => Value::Bool(lhs == rhs)
+                        Doc(lhs), Doc(rhs) => Value::Bool(lhs == rhs)
+                        Decimal(lhs), Decimal(rhs) => Value::Bool(lhs == rhs)
+                        Decimal(lhs), Int(rhs) => Value::Bool(lhs == Decimal::from(rhs))
+                        Int(lhs), Decimal(rhs) => Value::Bool(Decimal::from(lhs) == rhs)
+                        Date(lhs), Date(rhs) => Value::Bool(lhs == rhs)
+                        Time(lhs), Time(rhs) => Value::Bool(lhs == rhs)
+                        Timestamp(lhs), Timestamp(rhs) => Value::Bool(lhs == rhs)
+                        Interval(lhs), Interval(rhs) => Value::Bool(lhs == rhs)
                     }
                     Less {
                         Int(lhs), Int(rhs) => Value::Bool(lhs < rhs)
                         Float(lhs), Float(rhs) => Value::Bool(lhs < rhs)
+                        Int(lhs), Float(rhs) => Value::Bool((lhs as f64) < rhs)
+                        Float(lhs), Int(rhs) => Value::Bool(lhs < rhs as f64)
+                        Decimal(lhs), Float(rhs) => Value::Bool(lhs.to_float() < rhs)
+                        Float(lhs), Decimal(rhs) => Value::Bool(lhs < rhs.to_float())
+                        Decimal(lhs), Decimal(rhs) => Value::Bool(lhs < rhs)
+                        Decimal(lhs), Int(rhs) => Value::Bool(lhs < Decimal::from(rhs))
+                        Int(lhs), Decimal(rhs) => Value::Bool(Decimal::from(lhs) < rhs)
+                        Date(lhs), Date(rhs) => Value::Bool(lhs < rhs)
+                        Time(lhs), Time(rhs) => Value::Bool(lhs < rhs)
+                        Timestamp(lhs), Timestamp(rhs) => Value::Bool(lhs < rhs)
+                        Interval(lhs), Interval(rhs) => Value::Bool(lhs < rhs)
                     }
                     LessEq {
                         Int(lhs), Int(rhs) => Value::Bool(lhs <= rhs)
                         Float(lhs), Float(rhs) => Value::Bool(lhs <= rhs)
+                        Int(lhs), Float(rhs) => Value::Bool((lhs as f64) <= rhs)
+                        Float(lhs), Int(rhs) => Value::Bool(lhs <= rhs as f64)
+                        Decimal(lhs), Float(rhs) => Value::Bool(lhs.to_float() <= rhs)
+                        Float(lhs), Decimal(rhs) => Value::Bool(lhs <= rhs.to_float())
+                        Decimal(lhs), Decimal(rhs) => Value::Bool(lhs <= rhs)
+                        Decimal(lhs), Int(rhs) => Value::Bool(lhs <= Decimal::from(rhs))
+                        Int(lhs), Decimal(rhs) => Value::Bool(Decimal::from(lhs) <= rhs)
+                        Date(lhs), Date(rhs) => Value::Bool(lhs <= rhs)
+                        Time(lhs), Time(rhs) => Value::Bool(lhs <= rhs)
+                        Timestamp(lhs), Timestamp(rhs) => Value::Bool(lhs <= rhs)
+                        Interval(lhs), Interval(rhs) => Value::Bool(lhs <= rhs)
                     }
                     More {
                         Int(lhs), Int(rhs) => Value::Bool(lhs > rhs)
-                        Float(lhs), Float(rhs) => Value::Bool(lhs == rhs)
+                        Float(lhs), Float(rhs) => Value::Bool(lhs > rhs)
+                        Int(lhs), Float(rhs) => Value::Bool((lhs as f64) > rhs)
+                        Float(lhs), Int(rhs) => Value::Bool(lhs > rhs as f64)
+                        Decimal(lhs), Float(rhs) => Value::Bool(lhs.to_float() > rhs)
+                        Float(lhs), Decimal(rhs) => Value::Bool(lhs > rhs.to_float())
+                        Decimal(lhs), Decimal(rhs) => Value::Bool(lhs > rhs)
+                        Decimal(lhs), Int(rhs) => Value::Bool(lhs > Decimal::from(rhs))
+                        Int(lhs), Decimal(rhs) => Value::Bool(Decimal::from(lhs) > rhs)
+                        Date(lhs), Date(rhs) => Value::Bool(lhs > rhs)
+                        Time(lhs), Time(rhs) => Value::Bool(lhs > rhs)
+                        Timestamp(lhs), Timestamp(rhs) => Value::Bool(lhs > rhs)
+                        Interval(lhs), Interval(rhs) => Value::Bool(lhs > rhs)
                     }
                     MoreEq {
                         Int(lhs), Int(rhs) => Value::Bool(lhs >= rhs)
                         Float(lhs), Float(rhs) => Value::Bool(lhs >= rhs)
+                        Int(lhs), Float(rhs) => Value::Bool((lhs as f64) >= rhs)
+                        Float(lhs), Int(rhs) => Value::Bool(lhs >= rhs as f64)
+                        Decimal(lhs), Float(rhs) => Value::Bool(lhs.to_float() >= rhs)
+                        Float(lhs), Decimal(rhs) => Value::Bool(lhs >= rhs.to_float())
+                        Decimal(lhs), Decimal(rhs) => Value::Bool(lhs >= rhs)
+                        Decimal(lhs), Int(rhs) => Value::Bool(lhs >= Decimal::from(rhs))
+                        Int(lhs), Decimal(rhs) => Value::Bool(Decimal::from(lhs) >= rhs)
+                        Date(lhs), Date(rhs) => Value::Bool(lhs >= rhs)
+                        Time(lhs), Time(rhs) => Value::Bool(lhs >= rhs)
+                        Timestamp(lhs), Timestamp(rhs) => Value::Bool(lhs >= rhs)
+                        Interval(lhs), Interval(rhs) => Value::Bool(lhs >= rhs)
+                    }
+                    Index {
+                        List(list), Int(index) => {
+                            // Out of range indices give nil, like missing values.
+                            usize::try_from(index)
+                                .ok()
+                                .and_then(|index| list.into_iter().nth(index))
+                                .unwrap_or(Value::Nil)
+                        }
+                        Doc(fields), Str(field) => fields.get(&field).cloned().unwrap_or(Value::Nil)
+                    }
+                    Like {
+                        Str(lhs), Str(rhs) => Value::Bool(Self::like(&lhs, &rhs))
+                    }
+                    ILike {
+                        Str(lhs), Str(rhs) => {
+                            Value::Bool(Self::like(&lhs.to_lowercase(), &rhs.to_lowercase()))
+                        }
+                    }
+                    Contains {
+                        Str(lhs), Str(rhs) => Value::Bool(lhs.contains(&rhs))
+                    }
+                    IContains {
+                        Str(lhs), Str(rhs) => {
+                            Value::Bool(lhs.to_lowercase().contains(&rhs.to_lowercase()))
+                        }
+                    }
+                    StartsWith {
+                        Str(lhs), Str(rhs) => Value::Bool(lhs.starts_with(&rhs))
+                    }
+                    IStartsWith {
+                        Str(lhs), Str(rhs) => {
+                            Value::Bool(lhs.to_lowercase().starts_with(&rhs.to_lowercase()))
+                        }
+                    }
+                    EndsWith {
+                        Str(lhs), Str(rhs) => Value::Bool(lhs.ends_with(&rhs))
+                    }
+                    IEndsWith {
+                        Str(lhs), Str(rhs) => {
+                            Value::Bool(lhs.to_lowercase().ends_with(&rhs.to_lowercase()))
+                        }
                     }
                     And {
                         Bool(lhs), Bool(rhs) => Value::Bool(lhs && rhs)
@@ -562,7 +2046,7 @@ impl Display for Table {
             identifier, values, ..
         } in &self.columns
         {
-            let max_width = values.into_iter().fold(identifier.len(), |acc, value| {
+            let max_width = values.iter().fold(identifier.len(), |acc, value| {
                 acc.max(value.to_string().len())
             });
             max_widths.push(max_width);
@@ -595,14 +2079,14 @@ impl Display for Table {
         for j in 0..self.height() {
             writeln!(f)?;
             write!(f, "|")?;
-            for i in 0..self.width() {
-                let value_str = self.columns[i].values[j].to_string();
+            for (column, max_width) in self.columns.iter().zip(&max_widths) {
+                let value_str = column.values[j].to_string();
                 for _ in 0..PADDING {
                     write!(f, " ")?;
                 }
 
                 write!(f, "{value_str}")?;
-                for _ in 0..max_widths[i] - value_str.len() + PADDING {
+                for _ in 0..max_width - value_str.len() + PADDING {
                     write!(f, " ")?;
                 }
 
@@ -619,5 +2103,766 @@ pub struct Column {
     identifier: String,
     ty: Ty,
     optional: bool,
+    unique: bool,
+    default: Option<Value>,
+    check: Option<Expression>,
+    references: Option<Reference>,
     values: Vec<Value>,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{lexer::TokenIter, parser::CommandIter};
+
+    /// Runs every command, returning the output of the last one or the first error, failed
+    /// insertions included.
+    fn run(database: &mut Database, src: &str) -> Result<CommandRunOutput, String> {
+        let mut output = None;
+        for command in CommandIter::new(TokenIter::new(src.as_bytes())) {
+            let command = command.map_err(|err| err.to_string())?;
+            let result = database
+                .run_command(command)
+                .map_err(|err| err.to_string())?;
+            if let CommandRunOutput::RowsInserted { errs, .. } = &result {
+                if let Some(err) = errs.first() {
+                    return Err(err.to_string());
+                }
+            }
+
+            output = Some(result);
+        }
+
+        Ok(output.expect("no commands"))
+    }
+
+    /// The rows selected by the last command.
+    fn rows(database: &mut Database, src: &str) -> Vec<Vec<Value>> {
+        match run(database, src) {
+            Ok(CommandRunOutput::Selection { table }) => (0..table.height())
+                .map(|index| {
+                    table
+                        .columns
+                        .iter()
+                        .map(|column| column.values[index].clone())
+                        .collect()
+                })
+                .collect(),
+            Ok(output) => panic!("not a selection: {output}"),
+            Err(err) => panic!("{err}"),
+        }
+    }
+
+    /// The names of the columns selected by the last command.
+    fn names(database: &mut Database, src: &str) -> Vec<String> {
+        match run(database, src) {
+            Ok(CommandRunOutput::Selection { table }) => table
+                .columns
+                .iter()
+                .map(|column| column.identifier.clone())
+                .collect(),
+            Ok(output) => panic!("not a selection: {output}"),
+            Err(err) => panic!("{err}"),
+        }
+    }
+
+    /// Evaluates an expression without any columns.
+    fn evaluated(expression: &str) -> Result<Value, String> {
+        let mut database = Database::new();
+        let src = format!("new table One X: int; insert One X: 1; get One select {expression};");
+        match run(&mut database, &src)? {
+            CommandRunOutput::Selection { table } => Ok(table.columns[0].values[0].clone()),
+            output => panic!("not a selection: {output}"),
+        }
+    }
+
+    fn value(expression: &str) -> Value {
+        evaluated(expression).unwrap_or_else(|err| panic!("{expression}: {err}"))
+    }
+
+    #[test]
+    fn kleene_logic() {
+        let (t, f, n) = (Value::Bool(true), Value::Bool(false), Value::Nil);
+        for (lhs, rhs, and, or) in [
+            ("true", "true", &t, &t),
+            ("true", "false", &f, &t),
+            ("true", "nil", &n, &t),
+            ("false", "true", &f, &t),
+            ("false", "false", &f, &f),
+            ("false", "nil", &f, &n),
+            ("nil", "true", &n, &t),
+            ("nil", "false", &f, &n),
+            ("nil", "nil", &n, &n),
+        ] {
+            assert_eq!(value(&format!("{lhs} && {rhs}")), *and, "{lhs} && {rhs}");
+            assert_eq!(value(&format!("{lhs} || {rhs}")), *or, "{lhs} || {rhs}");
+        }
+    }
+
+    #[test]
+    fn nil_is_unknown() {
+        assert_eq!(value("1 + nil"), Value::Nil);
+        assert_eq!(value("nil == nil"), Value::Nil);
+        assert_eq!(value("1 < nil"), Value::Nil);
+        assert_eq!(value("(1 == nil) || true"), Value::Bool(true));
+
+        // Unknown filters count as false.
+        let mut database = Database::new();
+        let rows = rows(
+            &mut database,
+            r#"
+            new table P Name: str, Job: str?;
+            insert P { Name: "a", Job: "Pilot"; Name: "b"; };
+            get P select Name where Job == "Pilot" || Job == "Cook";
+            "#,
+        );
+        assert_eq!(rows, [[Value::Str("a".to_owned())]]);
+    }
+
+    #[test]
+    fn parentheses_group() {
+        assert_eq!(value("(1 + 2) * 3"), Value::Int(9));
+        assert_eq!(value("1 + 2 * 3"), Value::Int(7));
+        assert_eq!(value("(false && true) || true"), Value::Bool(true));
+        assert_eq!(value("false && (true || true)"), Value::Bool(false));
+    }
+
+    #[test]
+    fn int_float_promotion() {
+        assert_eq!(value("2 * 1.5"), Value::Float(3.0));
+        assert_eq!(value("1.5 * 2"), Value::Float(3.0));
+        assert_eq!(value("1 + 0.5"), Value::Float(1.5));
+        assert_eq!(value("0.5 - 1"), Value::Float(-0.5));
+        assert_eq!(value("3 / 2.0"), Value::Float(1.5));
+        assert_eq!(value("3.0 / 2"), Value::Float(1.5));
+        assert_eq!(value("3 / 2"), Value::Int(1));
+        assert_eq!(evaluated("1 / 0.0").unwrap_err(), "Division by zero.");
+        assert_eq!(evaluated("1.0 / 0").unwrap_err(), "Division by zero.");
+
+        assert_eq!(value("1 == 1.0"), Value::Bool(true));
+        assert_eq!(value("1.0 == 1"), Value::Bool(true));
+        assert_eq!(value("1 < 1.5"), Value::Bool(true));
+        assert_eq!(value("1.5 <= 1"), Value::Bool(false));
+        assert_eq!(value("2 > 1.5"), Value::Bool(true));
+        assert_eq!(value("1.5 >= 2"), Value::Bool(false));
+
+        let mut database = Database::new();
+        let rows = rows(
+            &mut database,
+            r#"
+            new table P Age: int, S: float;
+            insert P Age: 10, S: 0.5;
+            get P select Age * 1.5, Age + S where Age > 9.5;
+            "#,
+        );
+        assert_eq!(rows, [[Value::Float(15.0), Value::Float(10.5)]]);
+    }
+
+    #[test]
+    fn like() {
+        assert!(Table::like("", ""));
+        assert!(Table::like("", "%"));
+        assert!(!Table::like("", "_"));
+        assert!(Table::like("Pilot", "Pilot"));
+        assert!(!Table::like("Pilot", "pilot"));
+        assert!(Table::like("Pilot", "P%"));
+        assert!(Table::like("Pilot", "%t"));
+        assert!(Table::like("Pilot", "%lo%"));
+        assert!(Table::like("Pilot", "P_l_t"));
+        assert!(!Table::like("Pilot", "P_t"));
+        assert!(Table::like("aaab", "%a%b"));
+        assert!(!Table::like("aaab", "%a%c"));
+        assert!(Table::like("abcabd", "%abd"));
+
+        // Escaped wildcards only match themselves.
+        assert!(Table::like("100%", r"100\%"));
+        assert!(!Table::like("1000", r"100\%"));
+        assert!(Table::like("a_b", r"a\_b"));
+        assert!(!Table::like("axb", r"a\_b"));
+        assert!(Table::like(r"a\b", r"a\\b"));
+        assert!(!Table::like(r"a\", r"a\"));
+
+        // Wildcards match characters, not bytes.
+        assert!(Table::like("żółw", "_ół_"));
+        assert!(Table::like("日本語", "日%"));
+        assert!(!Table::like("日本語", "日_"));
+
+        assert_eq!(value(r#"("100%" like "100\\%")"#), Value::Bool(true));
+        assert_eq!(value(r#"("Pilot" ilike "pIL%")"#), Value::Bool(true));
+        assert_eq!(value(r#"("ŻÓŁW" ilike "żółw")"#), Value::Bool(true));
+    }
+
+    #[test]
+    fn calls() {
+        assert_eq!(value(r#"upper("ab")"#), Value::Str("AB".to_owned()));
+        assert_eq!(value(r#"length(trim("  ab "))"#), Value::Int(2));
+        assert_eq!(value(r#"length(upper("ab")) + 1"#), Value::Int(3));
+        assert!(evaluated("upper()").is_err());
+        assert!(evaluated(r#"upper("a", "b")"#).is_err());
+        assert!(evaluated(r#"nothing("a")"#).is_err());
+        assert!(evaluated(r#"upper("a""#).is_err());
+    }
+
+    #[test]
+    fn selection_names() {
+        let mut database = Database::new();
+        run(
+            &mut database,
+            r#"new table P Name: str, Age: int; insert P Name: "a", Age: 1;"#,
+        )
+        .unwrap();
+        assert_eq!(
+            names(
+                &mut database,
+                "get P select Name, upper(Name), Age + 1, Age * 2 as Double;"
+            ),
+            ["Name", "upper", "?column?", "Double"]
+        );
+        assert_eq!(
+            rows(
+                &mut database,
+                "get P select Age * 2 as Double, upper(Name);"
+            ),
+            [[Value::Int(2), Value::Str("A".to_owned())]]
+        );
+    }
+
+    #[test]
+    fn in_and_between() {
+        assert_eq!(value("2 in (1, 2, 3)"), Value::Bool(true));
+        assert_eq!(value("4 in (1, 2, 3)"), Value::Bool(false));
+        assert_eq!(value("4 not in (1, 2, 3)"), Value::Bool(true));
+        assert_eq!(value("4 in (1, nil)"), Value::Nil);
+        assert_eq!(value("1 in (1, nil)"), Value::Bool(true));
+        assert_eq!(value("1 + 1 in (2)"), Value::Bool(true));
+        assert_eq!(value("2 between 1 and 3"), Value::Bool(true));
+        assert_eq!(value("3 between 1 and 3"), Value::Bool(true));
+        assert_eq!(value("4 between 1 and 3"), Value::Bool(false));
+        assert_eq!(value("4 not between 1 and 3"), Value::Bool(true));
+        assert_eq!(value("1 + 1 between 1 and 1 + 1"), Value::Bool(true));
+        assert_eq!(value("2 between 1 and 3 && false"), Value::Bool(false));
+        assert!(evaluated("2 in 1, 2").is_err());
+        assert!(evaluated("2 between 1").is_err());
+        assert!(evaluated("2 not 1").is_err());
+
+        let mut database = Database::new();
+        let rows = rows(
+            &mut database,
+            r#"
+            new table P Name: str, Age: int;
+            insert P { Name: "a", Age: 1; Name: "b", Age: 5; Name: "c", Age: 9; };
+            get P select Name where Age not between 2 and 8 && Name in ("a", "b");
+            "#,
+        );
+        assert_eq!(rows, [[Value::Str("a".to_owned())]]);
+    }
+
+    #[test]
+    fn distinct() {
+        let mut database = Database::new();
+        run(
+            &mut database,
+            r#"
+            new table P Name: str, Job: str?, Score: float;
+            insert P {
+                Name: "a", Job: "Pilot", Score: 0.0;
+                Name: "b", Job: "Pilot", Score: 0.0;
+                Name: "c", Score: 0.0;
+                Name: "d", Score: 0.0;
+            };
+            "#,
+        )
+        .unwrap();
+
+        // Nil equals nil when telling rows apart.
+        assert_eq!(
+            rows(&mut database, "get P select distinct Job, Score;"),
+            [
+                [Value::Str("Pilot".to_owned()), Value::Float(0.0)],
+                [Value::Nil, Value::Float(0.0)],
+            ]
+        );
+        assert_eq!(rows(&mut database, "get P select Job;").len(), 4);
+        assert_eq!(
+            rows(
+                &mut database,
+                "get P select distinct Job where Job == \"Pilot\";"
+            ),
+            [[Value::Str("Pilot".to_owned())]]
+        );
+        assert!(run(&mut database, "get P select distinct;").is_err());
+    }
+
+    #[test]
+    fn bytes() {
+        assert_eq!(value(r#"x"""#), Value::Bytes(Vec::new()));
+        assert_eq!(value(r#"x"00fF7a""#), Value::Bytes(vec![0x00, 0xff, 0x7a]));
+        assert_eq!(value(r#"length(x"0a0b")"#), Value::Int(2));
+        assert_eq!(value(r#"x"0a" == x"0A""#), Value::Bool(true));
+        for invalid in [r#"x"0""#, r#"x"0g""#, r#"x"0a"#, "x'0a'"] {
+            assert!(evaluated(invalid).is_err(), "{invalid}");
+        }
+
+        // Without a string `x` is an identifier.
+        let mut database = Database::new();
+        let rows = rows(
+            &mut database,
+            r#"
+            new table B x: bytes;
+            insert B x: x"ff";
+            get B select x where x == x"ff";
+            "#,
+        );
+        assert_eq!(rows, [[Value::Bytes(vec![0xff])]]);
+    }
+
+    #[test]
+    fn docs() {
+        let doc = value(r#"{"a": 1, b: [1, null, -2], c: {d: "e"}}"#);
+        let Value::Doc(fields) = &doc else {
+            panic!("not a doc: {doc}");
+        };
+        assert_eq!(fields["a"], Value::Int(1));
+        assert_eq!(
+            fields["b"],
+            Value::List(vec![Value::Int(1), Value::Nil, Value::Int(-2)])
+        );
+        assert_eq!(
+            doc.to_string(),
+            r#"{"a": 1, "b": [1, nil, -2], "c": {"d": "e"}}"#
+        );
+        assert_eq!(value("{}"), Value::Doc(std::collections::BTreeMap::new()));
+
+        assert_eq!(value(r#"{a: {b: 2}}.a.b"#), Value::Int(2));
+        assert_eq!(value(r#"{a: {b: 2}}["a"]["b"]"#), Value::Int(2));
+        assert_eq!(value(r#"{a: [1, 2]}.a[1] + 1"#), Value::Int(3));
+        assert_eq!(value(r#"{a: 1}.b"#), Value::Nil);
+        for invalid in ["{a 1}", "{a: 1 b: 2}", "{a: 1", "{a: 1}."] {
+            assert!(evaluated(invalid).is_err(), "{invalid}");
+        }
+
+        // Selected fields are named after the last field.
+        let mut database = Database::new();
+        run(
+            &mut database,
+            r#"new table P Info: doc; insert P Info: {name: {first: "a"}, age: 1};"#,
+        )
+        .unwrap();
+        assert_eq!(
+            names(
+                &mut database,
+                r#"get P select Info.name.first, Info["age"];"#
+            ),
+            ["first", "age"]
+        );
+        assert_eq!(
+            rows(
+                &mut database,
+                "get P select Info.age where Info.name.first == \"a\";"
+            ),
+            [[Value::Int(1)]]
+        );
+    }
+
+    #[test]
+    fn negation() {
+        assert_eq!(value("-5"), Value::Int(-5));
+        assert_eq!(value("--5"), Value::Int(5));
+        assert_eq!(value("-1.5"), Value::Float(-1.5));
+        assert_eq!(value("-1.50d").to_string(), "-1.50");
+        assert_eq!(value("2 - -1"), Value::Int(3));
+        assert_eq!(value("-2 * 3"), Value::Int(-6));
+        assert_eq!(value("-(1 + 2)"), Value::Int(-3));
+        assert_eq!(value("-[1, 2][1]"), Value::Int(-2));
+        assert_eq!(value("-X"), Value::Int(-1));
+        assert_eq!(value("1 - -X * 2"), Value::Int(3));
+        assert_eq!(value("-(interval \"1 day\")"), value("interval \"-1 day\""));
+        assert_eq!(value("-nil"), Value::Nil);
+        assert_eq!(value("null"), Value::Nil);
+        assert_eq!(
+            value("[-1, null]"),
+            Value::List(vec![Value::Int(-1), Value::Nil])
+        );
+        assert!(evaluated("-\"a\"").is_err());
+        assert!(evaluated("[-X]").is_err());
+        assert!(evaluated("-").is_err());
+
+        let mut database = Database::new();
+        let rows = rows(
+            &mut database,
+            r#"
+            new table P Name: str, Age: int = -1, Job: str?;
+            insert P { Name: "a", Age: -5, Job: null; Name: "b"; Name: "c", Age: 3; };
+            get P select Name, -Age where Age < -1 || Age > -1;
+            "#,
+        );
+        assert_eq!(
+            rows,
+            [
+                [Value::Str("a".to_owned()), Value::Int(5)],
+                [Value::Str("c".to_owned()), Value::Int(-3)],
+            ]
+        );
+    }
+
+    #[test]
+    fn contains() {
+        assert_eq!(value("contains([1, 2], 2)"), Value::Bool(true));
+        assert_eq!(value("contains([1, 2], 3)"), Value::Bool(false));
+        assert_eq!(value("contains([1.0], 1)"), Value::Bool(true));
+        assert_eq!(value("contains([1], 1.0)"), Value::Bool(true));
+        assert_eq!(value("contains([1, nil], 2)"), Value::Nil);
+        assert_eq!(value("contains([1, nil], 1)"), Value::Bool(true));
+        assert_eq!(value("contains([1], nil)"), Value::Nil);
+        assert_eq!(value("contains(nil, 1)"), Value::Nil);
+        assert_eq!(value("contains([], 1)"), Value::Bool(false));
+        assert!(evaluated("contains(1, 1)").is_err());
+
+        let mut database = Database::new();
+        let rows = rows(
+            &mut database,
+            r#"
+            new type Job enum ("Pilot", "Cook");
+            new table P Name: str, Jobs: [Job];
+            insert P { Name: "a", Jobs: ["Pilot"]; Name: "b", Jobs: ["Cook"]; };
+            get P select Name where contains(Jobs, "Pilot");
+            "#,
+        );
+        assert_eq!(rows, [[Value::Str("a".to_owned())]]);
+    }
+
+    #[test]
+    fn unique_selection_names() {
+        let mut database = Database::new();
+        run(
+            &mut database,
+            r#"
+            new table P N: str, M: str;
+            new table Q upper: str, "upper_2": str;
+            insert P N: "a", M: "b";
+            "#,
+        )
+        .unwrap();
+        assert_eq!(
+            names(
+                &mut database,
+                r#"get P select upper(N), upper(M), N + M, N + M, upper(N) as "upper_2";"#
+            ),
+            ["upper", "upper_2", "?column?", "?column?_2", "upper_2"]
+        );
+        assert_eq!(
+            names(&mut database, "get P select N as upper, upper(M);"),
+            ["upper", "upper_2"]
+        );
+
+        run(
+            &mut database,
+            "insert Q from get P select upper(N), upper(M);",
+        )
+        .unwrap();
+        assert_eq!(
+            rows(&mut database, r#"get Q select upper, "upper_2";"#),
+            [[Value::Str("A".to_owned()), Value::Str("B".to_owned())]]
+        );
+        assert_eq!(
+            run(
+                &mut database,
+                "insert Q from get P select N as upper, M as upper;"
+            )
+            .unwrap_err(),
+            "Column \"upper\" is selected more than once."
+        );
+        assert_eq!(rows(&mut database, "get Q;").len(), 1);
+    }
+
+    #[test]
+    fn decimals_and_floats() {
+        assert_eq!(value("1.5d + 0.25"), Value::Float(1.75));
+        assert_eq!(value("0.25 - 1.5d"), Value::Float(-1.25));
+        assert_eq!(value("1.5d * 2.0"), Value::Float(3.0));
+        assert_eq!(value("3.0 / 1.5d"), Value::Float(2.0));
+        assert_eq!(evaluated("1.5d / 0.0").unwrap_err(), "Division by zero.");
+        assert_eq!(evaluated("1.5 / 0.0d").unwrap_err(), "Division by zero.");
+        assert_eq!(value("1.5d == 1.5"), Value::Bool(true));
+        assert_eq!(value("1.5 < 1.50d"), Value::Bool(false));
+        assert_eq!(value("1.5d <= 1.5"), Value::Bool(true));
+        assert_eq!(value("2.0 > 1.5d"), Value::Bool(true));
+        assert_eq!(value("1.5d >= 2.0"), Value::Bool(false));
+
+        // Floats are stored in decimal columns when their digits fit.
+        let mut database = Database::new();
+        run(
+            &mut database,
+            r#"
+            new table P Name: str, Price: decimal(4, 2);
+            insert P { Name: "a", Price: 1.5; Name: "b", Price: 12.25; Name: "c", Price: -0.1; };
+            "#,
+        )
+        .unwrap();
+        assert_eq!(
+            rows(
+                &mut database,
+                "get P select Name, Price + 1.5 where Price > 1.5;"
+            ),
+            [[Value::Str("b".to_owned()), Value::Float(13.75)]]
+        );
+        assert_eq!(
+            rows(&mut database, "get P select Price where Name == \"c\";")[0][0].to_string(),
+            "-0.10"
+        );
+        for price in ["1.125", "123.5"] {
+            let src = format!("insert P Name: \"d\", Price: {price};");
+            assert!(run(&mut database, &src).is_err(), "{price}");
+        }
+    }
+
+    /// Tables A, B referencing A and C referencing B, with the given behaviors on removal.
+    fn chain(b: &str, c: &str) -> Database {
+        let mut database = Database::new();
+        let src = format!(
+            "
+            new table A N: int;
+            new table B A: int? references A on remove {b};
+            new table C B: int? references B on remove {c};
+            insert A {{ N: 0; N: 1; }};
+            insert B {{ A: 0; A: 0; A: 1; }};
+            insert C {{ B: 0; B: 1; B: 2; }};
+            "
+        );
+        run(&mut database, &src).unwrap();
+        database
+    }
+
+    #[test]
+    fn remove_chained_cascades() {
+        let mut database = chain("cascade", "cascade");
+        assert!(matches!(
+            run(&mut database, "remove A where N == 0;"),
+            Ok(CommandRunOutput::Removed { count: 1 })
+        ));
+        assert_eq!(rows(&mut database, "get A select N;"), [[Value::Int(1)]]);
+        assert_eq!(rows(&mut database, "get B select Id;"), [[Value::Int(2)]]);
+        assert_eq!(rows(&mut database, "get C select B;"), [[Value::Int(2)]]);
+    }
+
+    #[test]
+    fn remove_restricted_partway() {
+        let mut database = chain("cascade", "restrict");
+        assert_eq!(
+            run(&mut database, "remove A where N == 0;").unwrap_err(),
+            "Rows are still referenced by column \"B\" of table \"C\"."
+        );
+        // Nothing gets removed.
+        assert_eq!(rows(&mut database, "get A;").len(), 2);
+        assert_eq!(rows(&mut database, "get B;").len(), 3);
+        assert_eq!(rows(&mut database, "get C;").len(), 3);
+
+        // Removing the rows of C first lets the cascade through.
+        run(
+            &mut database,
+            "remove C where B < 2; remove A where N == 0;",
+        )
+        .unwrap();
+        assert_eq!(rows(&mut database, "get B select Id;"), [[Value::Int(2)]]);
+    }
+
+    #[test]
+    fn remove_sets_nil_through_cascades() {
+        let mut database = chain("cascade", "set nil");
+        run(&mut database, "remove A where N == 0;").unwrap();
+        assert_eq!(rows(&mut database, "get B select Id;"), [[Value::Int(2)]]);
+        assert_eq!(
+            rows(&mut database, "get C select B;"),
+            [[Value::Nil], [Value::Nil], [Value::Int(2)]]
+        );
+
+        let mut database = chain("set nil", "set nil");
+        run(&mut database, "remove A where N == 0;").unwrap();
+        assert_eq!(
+            rows(&mut database, "get B select A;"),
+            [[Value::Nil], [Value::Nil], [Value::Int(1)]]
+        );
+        assert_eq!(rows(&mut database, "get C select B;").len(), 3);
+    }
+
+    #[test]
+    fn reference_definitions() {
+        let mut database = Database::new();
+        run(&mut database, "new table A N: int;").unwrap();
+        assert_eq!(
+            run(
+                &mut database,
+                "new table B A: int references A on remove set nil;"
+            )
+            .unwrap_err(),
+            "Column \"A\" has to be optional to be set to nil on removal."
+        );
+        assert_eq!(
+            run(&mut database, "new table B A: str references A;").unwrap_err(),
+            "Column \"A\" references a key of type: int."
+        );
+    }
+
+    #[test]
+    fn primary_key_definitions() {
+        let mut database = Database::new();
+        assert_eq!(
+            run(
+                &mut database,
+                "new table P primary key (A, B), A: int, B: str?;"
+            )
+            .unwrap_err(),
+            "Key column \"B\" cannot be optional."
+        );
+        assert_eq!(
+            run(&mut database, "new table P primary key (C), A: int;").unwrap_err(),
+            "Column \"C\" does not exist."
+        );
+        run(
+            &mut database,
+            "new table P primary key (A, B), A: int, B: str;",
+        )
+        .unwrap();
+    }
+
+    #[test]
+    fn check_definitions() {
+        let mut database = Database::new();
+        assert_eq!(
+            run(&mut database, "new table T A: int check (B > 0);").unwrap_err(),
+            "Column \"B\" does not exist."
+        );
+        assert_eq!(
+            run(
+                &mut database,
+                "new table T A: int check (abs(A) in (1, C));"
+            )
+            .unwrap_err(),
+            "Column \"C\" does not exist."
+        );
+        run(
+            &mut database,
+            "new table T A: int check (A < B && Id >= 0), B: int check (B between 0 and 9);",
+        )
+        .unwrap();
+        assert!(run(&mut database, "insert T A: 1, B: 2;").is_ok());
+        assert!(run(&mut database, "insert T A: 3, B: 2;").is_err());
+    }
+
+    #[test]
+    fn subqueries_run_once() {
+        let calls = Rc::new(std::cell::Cell::new(0));
+        let mut database = Database::new();
+        database.register_function("tick", Signature::new([], Ty::Int), {
+            let calls = calls.clone();
+            move |_| {
+                calls.set(calls.get() + 1);
+                Value::Int(calls.get())
+            }
+        });
+        run(
+            &mut database,
+            "new table N X: int; insert N { X: 1; X: 2; X: 3; };
+            new table One X: int; insert One X: 1;",
+        )
+        .unwrap();
+
+        let queries = [
+            ("get N select X where X == (get One select tick());", 1),
+            (
+                "get N select X where X in (get One select tick()) || X == 3;",
+                2,
+            ),
+            (
+                "get N select X, (get One select tick()) as T where X < 3;",
+                2,
+            ),
+            (
+                "get N select X where X + 1 between (get One select tick()) and 3;",
+                2,
+            ),
+        ];
+        for (query, count) in queries {
+            calls.set(0);
+            assert_eq!(rows(&mut database, query).len(), count, "{query}");
+            assert_eq!(calls.get(), 1, "{query}");
+        }
+
+        run(
+            &mut database,
+            "remove N where X == (get One select tick() + 1);",
+        )
+        .unwrap();
+        assert_eq!(rows(&mut database, "get N;").len(), 2);
+    }
+
+    #[test]
+    fn registered_function_types() {
+        let mut database = Database::new();
+        run(&mut database, r#"new type Job enum ("Pilot", "Cook");"#).unwrap();
+        let job = Ty::Enum(database.types["Job"].clone());
+        database.register_function("half", Signature::new([Ty::Float], Ty::Float), |args| {
+            let [Value::Float(float)] = args else {
+                unreachable!();
+            };
+            Value::Float(float / 2.0)
+        });
+        let total = Signature::new([Ty::List(Box::new(Ty::Int))], Ty::Float);
+        database.register_function("total", total, |args| {
+            let [Value::List(values)] = args else {
+                unreachable!();
+            };
+            let ints = values.iter().map(|value| match value {
+                Value::Int(int) => *int,
+                _ => 0,
+            });
+            Value::Int(ints.sum())
+        });
+        let price = Ty::Decimal {
+            precision: 4,
+            scale: 2,
+        };
+        database.register_function("price", Signature::new([price.clone()], price), |args| {
+            args[0].clone()
+        });
+        database.register_function("pilot", Signature::new([job], Ty::Bool), |args| {
+            Value::Bool(args[0].variant() == "Pilot")
+        });
+        database.register_function("name", Signature::new([], Ty::Int), |_| {
+            Value::Str("a".to_owned())
+        });
+        run(&mut database, "new table One X: int; insert One X: 1;").unwrap();
+
+        let mut value = |expression: &str| {
+            let src = format!("get One select {expression};");
+            run(&mut database, &src).map(|output| match output {
+                CommandRunOutput::Selection { table } => table.columns[0].values[0].clone(),
+                output => panic!("not a selection: {output}"),
+            })
+        };
+        assert_eq!(value("half(3)"), Ok(Value::Float(1.5)));
+        assert_eq!(value("half(nil)"), Ok(Value::Nil));
+        assert_eq!(value("total([1, nil, 2])"), Ok(Value::Float(3.0)));
+        assert_eq!(value("total([2.0])"), Ok(Value::Float(2.0)));
+        assert_eq!(value("price(1.5)").unwrap().to_string(), "1.50");
+        assert_eq!(value("price(1.005d)").unwrap().to_string(), "1.01");
+        assert_eq!(value(r#"pilot("Pilot")"#), Ok(Value::Bool(true)));
+        for invalid in [
+            "half(\"a\")",
+            "total([1.5])",
+            "total(1)",
+            "price(100)",
+            "pilot(\"Nobody\")",
+        ] {
+            assert_eq!(
+                value(invalid).unwrap_err().split(" of").next(),
+                Some("Argument 0"),
+                "{invalid}"
+            );
+        }
+        assert_eq!(
+            value("name()").unwrap_err(),
+            "Function \"name\" is expected to return a type: int."
+        );
+    }
+}
@@ -0,0 +1,316 @@
+use std::{
+    fmt::Formatter,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use crate::Value;
+
+// Dates are stored as days since 1970-01-01 and timestamps as seconds since then, without time
+// zones. Times are seconds since midnight and intervals are a number of seconds.
+
+pub(crate) const SECONDS_PER_DAY: i64 = 86_400;
+
+/// 0000-01-01
+const MIN_DAYS: i64 = days_from_civil(0, 1, 1);
+/// 9999-12-31
+const MAX_DAYS: i64 = days_from_civil(9999, 12, 31);
+
+/// Days since 1970-01-01 of a date in the proleptic Gregorian calendar, see
+/// <http://howardhinnant.github.io/date_algorithms.html>.
+const fn days_from_civil(year: i64, month: i64, day: i64) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let year_of_era = year - era * 400;
+    // Years start in march, so that the leap day is the last one.
+    let day_of_year = (153 * ((month + 9) % 12) + 2) / 5 + day - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    era * 146_097 + day_of_era - 719_468
+}
+
+/// The year, month and day of a date given as days since 1970-01-01.
+pub(crate) fn civil_from_days(days: i64) -> (i64, i64, i64) {
+    let days = days + 719_468;
+    let era = days.div_euclid(146_097);
+    let day_of_era = days - era * 146_097;
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * month + 2) / 5 + 1;
+    let month = if month < 10 { month + 3 } else { month - 9 };
+    (year_of_era + era * 400 + i64::from(month <= 2), month, day)
+}
+
+fn days_in_month(year: i64, month: i64) -> i64 {
+    match month {
+        2 if year % 4 == 0 && (year % 100 != 0 || year % 400 == 0) => 29,
+        2 => 28,
+        4 | 6 | 9 | 11 => 30,
+        _ => 31,
+    }
+}
+
+/// Parses a number of exactly `len` digits.
+fn parse_digits(str: &str, len: usize) -> Option<i64> {
+    if str.len() != len || !str.bytes().all(|byte| byte.is_ascii_digit()) {
+        return None;
+    }
+
+    str.parse().ok()
+}
+
+/// Parses a `YYYY-MM-DD` date into days since 1970-01-01.
+pub(crate) fn parse_date(str: &str) -> Option<i64> {
+    let mut parts = str.split('-');
+    let year = parse_digits(parts.next()?, 4)?;
+    let month = parse_digits(parts.next()?, 2)?;
+    let day = parse_digits(parts.next()?, 2)?;
+    if parts.next().is_some()
+        || !(1..=12).contains(&month)
+        || !(1..=days_in_month(year, month)).contains(&day)
+    {
+        return None;
+    }
+
+    Some(days_from_civil(year, month, day))
+}
+
+/// Parses a `HH:MM` or `HH:MM:SS` time into seconds since midnight.
+pub(crate) fn parse_time(str: &str) -> Option<i64> {
+    let mut parts = str.split(':');
+    let hour = parse_digits(parts.next()?, 2)?;
+    let minute = parse_digits(parts.next()?, 2)?;
+    let second = parts
+        .next()
+        .map_or(Some(0), |second| parse_digits(second, 2))?;
+    if parts.next().is_some() || hour >= 24 || minute >= 60 || second >= 60 {
+        return None;
+    }
+
+    Some(hour * 3600 + minute * 60 + second)
+}
+
+/// Parses a `YYYY-MM-DDTHH:MM:SS` timestamp into seconds since 1970-01-01, the date and time
+/// can also be separated by a space and the time can end with a `Z`.
+pub(crate) fn parse_timestamp(str: &str) -> Option<i64> {
+    let (date, time) = str.split_once(['T', ' '])?;
+    let time = time.strip_suffix('Z').unwrap_or(time);
+    Some(parse_date(date)? * SECONDS_PER_DAY + parse_time(time)?)
+}
+
+/// Parses an interval like `1 day 12 hours` into seconds.
+pub(crate) fn parse_interval(str: &str) -> Option<i64> {
+    let mut parts = str.split_whitespace();
+    let mut seconds = 0i64;
+    let mut empty = true;
+    while let Some(count) = parts.next() {
+        let count = count.parse::<i64>().ok()?;
+        let unit = match parts.next()? {
+            "week" | "weeks" => 7 * SECONDS_PER_DAY,
+            "day" | "days" => SECONDS_PER_DAY,
+            "hour" | "hours" => 3600,
+            "minute" | "minutes" => 60,
+            "second" | "seconds" => 1,
+            _ => return None,
+        };
+
+        seconds = seconds.checked_add(count.checked_mul(unit)?)?;
+        empty = false;
+    }
+
+    (!empty).then_some(seconds)
+}
+
+/// A date value, if `days` is within the years 0 and 9999.
+pub(crate) fn date(days: i64) -> Option<Value> {
+    (MIN_DAYS..=MAX_DAYS)
+        .contains(&days)
+        .then_some(Value::Date(days))
+}
+
+/// A timestamp value, if `seconds` is within the years 0 and 9999.
+pub(crate) fn timestamp(seconds: i64) -> Option<Value> {
+    (MIN_DAYS * SECONDS_PER_DAY..(MAX_DAYS + 1) * SECONDS_PER_DAY)
+        .contains(&seconds)
+        .then_some(Value::Timestamp(seconds))
+}
+
+/// Adds an interval to a date, resulting in a timestamp unless the interval is whole days.
+pub(crate) fn add_to_date(days: i64, seconds: i64) -> Option<Value> {
+    if seconds % SECONDS_PER_DAY == 0 {
+        date(days.checked_add(seconds / SECONDS_PER_DAY)?)
+    } else {
+        timestamp(days.checked_mul(SECONDS_PER_DAY)?.checked_add(seconds)?)
+    }
+}
+
+/// Seconds since 1970-01-01 of the current time.
+pub(crate) fn now() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |duration| duration.as_secs() as i64)
+}
+
+pub(crate) fn fmt_date(f: &mut Formatter<'_>, days: i64) -> std::fmt::Result {
+    let (year, month, day) = civil_from_days(days);
+    write!(f, "{year:04}-{month:02}-{day:02}")
+}
+
+pub(crate) fn fmt_time(f: &mut Formatter<'_>, seconds: i64) -> std::fmt::Result {
+    write!(
+        f,
+        "{:02}:{:02}:{:02}",
+        seconds / 3600,
+        seconds / 60 % 60,
+        seconds % 60
+    )
+}
+
+pub(crate) fn fmt_timestamp(f: &mut Formatter<'_>, seconds: i64) -> std::fmt::Result {
+    fmt_date(f, seconds.div_euclid(SECONDS_PER_DAY))?;
+    write!(f, "T")?;
+    fmt_time(f, seconds.rem_euclid(SECONDS_PER_DAY))
+}
+
+/// Formats an interval the way it gets parsed, like `1 day 12 hours`.
+pub(crate) fn fmt_interval(f: &mut Formatter<'_>, seconds: i64) -> std::fmt::Result {
+    if seconds == 0 {
+        return write!(f, "0 seconds");
+    }
+
+    let mut rest = seconds;
+    let mut first = true;
+    for (unit, name) in [
+        (SECONDS_PER_DAY, "day"),
+        (3600, "hour"),
+        (60, "minute"),
+        (1, "second"),
+    ] {
+        let count = rest / unit;
+        rest %= unit;
+        if count == 0 {
+            continue;
+        }
+
+        if !first {
+            write!(f, " ")?;
+        }
+
+        write!(
+            f,
+            "{count} {name}{}",
+            if count.abs() == 1 { "" } else { "s" }
+        )?;
+        first = false;
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn days_from_civil_and_back() {
+        assert_eq!(days_from_civil(1970, 1, 1), 0);
+        assert_eq!(days_from_civil(1969, 12, 31), -1);
+        assert_eq!(days_from_civil(2000, 3, 1), 11_017);
+        assert_eq!(civil_from_days(11_016), (2000, 2, 29));
+        assert_eq!(civil_from_days(MIN_DAYS), (0, 1, 1));
+        assert_eq!(civil_from_days(MAX_DAYS), (9999, 12, 31));
+
+        for days in (MIN_DAYS..=MAX_DAYS).step_by(97) {
+            let (year, month, day) = civil_from_days(days);
+            assert_eq!(days_from_civil(year, month, day), days);
+        }
+    }
+
+    #[test]
+    fn leap_years() {
+        assert!(parse_date("2024-02-29").is_some());
+        assert!(parse_date("2023-02-29").is_none());
+        assert!(parse_date("2000-02-29").is_some());
+        assert!(parse_date("1900-02-29").is_none());
+        assert!(parse_date("0000-02-29").is_some());
+        assert!(parse_date("9996-02-29").is_some());
+        assert_eq!(
+            parse_date("2024-03-01").unwrap() - parse_date("2024-02-28").unwrap(),
+            2
+        );
+    }
+
+    #[test]
+    fn year_range() {
+        assert_eq!(parse_date("0000-01-01"), Some(MIN_DAYS));
+        assert_eq!(parse_date("9999-12-31"), Some(MAX_DAYS));
+        assert!(date(MIN_DAYS).is_some());
+        assert!(date(MIN_DAYS - 1).is_none());
+        assert!(date(MAX_DAYS).is_some());
+        assert!(date(MAX_DAYS + 1).is_none());
+
+        let last = parse_timestamp("9999-12-31T23:59:59").unwrap();
+        assert!(timestamp(last).is_some());
+        assert!(timestamp(last + 1).is_none());
+        assert!(timestamp(MIN_DAYS * SECONDS_PER_DAY - 1).is_none());
+
+        assert!(add_to_date(MAX_DAYS, SECONDS_PER_DAY).is_none());
+        assert!(add_to_date(MIN_DAYS, -1).is_none());
+        assert!(matches!(
+            add_to_date(MAX_DAYS, 3600),
+            Some(Value::Timestamp(_))
+        ));
+        assert!(add_to_date(MAX_DAYS, i64::MAX).is_none());
+
+        assert_eq!(Value::Date(MIN_DAYS).to_string(), "0000-01-01");
+        assert_eq!(Value::Timestamp(last).to_string(), "9999-12-31T23:59:59");
+    }
+
+    #[test]
+    fn parse_invalid() {
+        assert!(parse_date("2024-1-01").is_none());
+        assert!(parse_date("2024-13-01").is_none());
+        assert!(parse_date("2024-04-31").is_none());
+        assert!(parse_time("24:00").is_none());
+        assert!(parse_time("12:60").is_none());
+        assert!(parse_timestamp("2024-01-01").is_none());
+        assert_eq!(
+            parse_timestamp("1970-01-02 00:00:01Z"),
+            Some(SECONDS_PER_DAY + 1)
+        );
+        assert!(parse_interval("").is_none());
+        assert!(parse_interval("1").is_none());
+        assert!(parse_interval("1 fortnight").is_none());
+        assert!(parse_interval("9223372036854775807 days").is_none());
+    }
+
+    #[test]
+    fn interval_round_trip() {
+        assert_eq!(parse_interval("2 days 3 hours"), Some(183_600));
+        assert_eq!(parse_interval("1 week"), Some(7 * SECONDS_PER_DAY));
+        assert_eq!(parse_interval("1 day -1 hour"), Some(82_800));
+        assert_eq!(Value::Interval(82_800).to_string(), "23 hours");
+        assert_eq!(
+            Value::Interval(-90_061).to_string(),
+            "-1 day -1 hour -1 minute -1 second"
+        );
+        assert_eq!(Value::Interval(-82_800).to_string(), "-23 hours");
+
+        for seconds in [
+            0,
+            1,
+            -1,
+            59,
+            -3600,
+            90_061,
+            -90_061,
+            -82_800,
+            -7 * SECONDS_PER_DAY,
+            i64::MAX,
+            i64::MIN + 1,
+        ] {
+            let interval = Value::Interval(seconds).to_string();
+            assert_eq!(parse_interval(&interval), Some(seconds), "{interval}");
+        }
+    }
+}
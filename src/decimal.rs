@@ -0,0 +1,402 @@
+use std::{cmp::Ordering, fmt::Display};
+
+/// Most digits a decimal can have.
+pub const MAX_PRECISION: u8 = 38;
+
+/// Digits kept after the point when dividing, beyond those of the operands.
+const DIVISION_SCALE: u8 = 6;
+
+/// An exact fixed-point number, `mantissa / 10^scale`.
+#[derive(Debug, Clone, Copy)]
+pub struct Decimal {
+    mantissa: i128,
+    scale: u8,
+}
+
+fn pow10(exponent: u8) -> Option<i128> {
+    10i128.checked_pow(exponent as u32)
+}
+
+/// Divides rounding half away from zero.
+fn div_rounded(lhs: i128, rhs: i128) -> Option<i128> {
+    let quotient = lhs.checked_div(rhs)?;
+    let remainder = lhs % rhs;
+    if remainder.unsigned_abs() >= rhs.unsigned_abs() - remainder.unsigned_abs() {
+        quotient.checked_add(if (lhs < 0) == (rhs < 0) { 1 } else { -1 })
+    } else {
+        Some(quotient)
+    }
+}
+
+impl Decimal {
+    /// # Panics
+    /// if `scale > MAX_PRECISION`
+    pub fn new(mantissa: i128, scale: u8) -> Self {
+        assert!(scale <= MAX_PRECISION, "scale too large");
+        Self { mantissa, scale }
+    }
+
+    pub fn mantissa(&self) -> i128 {
+        self.mantissa
+    }
+
+    pub fn scale(&self) -> u8 {
+        self.scale
+    }
+
+    /// Count of digits, which is at least the scale.
+    pub fn precision(&self) -> u8 {
+        let digits = self
+            .mantissa
+            .unsigned_abs()
+            .checked_ilog10()
+            .map_or(1, |log| log + 1);
+        (digits as u8).max(self.scale)
+    }
+
+    /// Parses digits with an optional fractional part, like `12.50`.
+    pub(crate) fn parse(str: &str) -> Option<Self> {
+        let (int, fraction) = str.split_once('.').unwrap_or((str, ""));
+        if int.is_empty()
+            || !int
+                .bytes()
+                .chain(fraction.bytes())
+                .all(|byte| byte.is_ascii_digit())
+            || int.len() + fraction.len() > MAX_PRECISION as usize
+        {
+            return None;
+        }
+
+        Some(Self {
+            mantissa: format!("{int}{fraction}").parse().ok()?,
+            scale: fraction.len() as u8,
+        })
+    }
+
+    /// The shortest decimal that reads back as `float`, `None` for infinities, NaN and floats
+    /// with too many digits.
+    pub(crate) fn from_float(float: f64) -> Option<Self> {
+        let decimal = Self::parse(&float.abs().to_string())?;
+        if float.is_sign_negative() {
+            Some(Self::new(-decimal.mantissa, decimal.scale))
+        } else {
+            Some(decimal)
+        }
+    }
+
+    /// The nearest float.
+    pub(crate) fn to_float(self) -> f64 {
+        self.to_string().parse().expect("decimals are valid floats")
+    }
+
+    /// The same number with another scale, rounding half away from zero when there are fewer
+    /// digits after the point.
+    pub(crate) fn rescaled(self, scale: u8) -> Option<Self> {
+        if scale > MAX_PRECISION {
+            return None;
+        }
+
+        let mantissa = match scale.cmp(&self.scale) {
+            Ordering::Equal => self.mantissa,
+            Ordering::Greater => self.mantissa.checked_mul(pow10(scale - self.scale)?)?,
+            Ordering::Less => div_rounded(self.mantissa, pow10(self.scale - scale)?)?,
+        };
+
+        Some(Self { mantissa, scale })
+    }
+
+    /// The same number without trailing zeros after the point.
+    pub(crate) fn normalized(mut self) -> Self {
+        while self.scale > 0 && self.mantissa % 10 == 0 {
+            self.mantissa /= 10;
+            self.scale -= 1;
+        }
+
+        self
+    }
+
+    pub(crate) fn checked_add(self, rhs: Self) -> Option<Self> {
+        let scale = self.scale.max(rhs.scale);
+        let (lhs, rhs) = (self.rescaled(scale)?, rhs.rescaled(scale)?);
+        Some(Self {
+            mantissa: lhs.mantissa.checked_add(rhs.mantissa)?,
+            scale,
+        })
+    }
+
+    pub(crate) fn checked_sub(self, rhs: Self) -> Option<Self> {
+        self.checked_add(Self {
+            mantissa: rhs.mantissa.checked_neg()?,
+            scale: rhs.scale,
+        })
+    }
+
+    pub(crate) fn checked_mul(self, rhs: Self) -> Option<Self> {
+        let product = Self {
+            mantissa: self.mantissa.checked_mul(rhs.mantissa)?,
+            scale: self.scale + rhs.scale,
+        };
+
+        if product.scale > MAX_PRECISION {
+            return product.normalized().rescaled(MAX_PRECISION);
+        }
+
+        Some(product)
+    }
+
+    /// Divides keeping a few more digits after the point than the operands have, `None` on
+    /// overflow or division by zero.
+    pub(crate) fn checked_div(self, rhs: Self) -> Option<Self> {
+        let scale = (self.scale.max(rhs.scale) + DIVISION_SCALE).min(MAX_PRECISION);
+        // self / rhs = (lhs * 10^(scale + rhs.scale - self.scale) / rhs) / 10^scale
+        let lhs = self.rescaled(scale + rhs.scale).or_else(|| {
+            // Losing digits of the result is better than failing.
+            self.rescaled(self.scale.max(rhs.scale))
+        })?;
+
+        let mantissa = div_rounded(lhs.mantissa, rhs.mantissa)?;
+        Self {
+            mantissa,
+            scale: lhs.scale - rhs.scale,
+        }
+        .rescaled(scale)
+    }
+
+    pub(crate) fn abs(self) -> Option<Self> {
+        Some(Self {
+            mantissa: self.mantissa.checked_abs()?,
+            scale: self.scale,
+        })
+    }
+
+    /// Rounds half away from zero to at most `scale` digits after the point.
+    pub(crate) fn round(self, scale: u8) -> Option<Self> {
+        self.rescaled(scale.min(self.scale))
+    }
+
+    pub(crate) fn floor(self) -> Option<Self> {
+        Some(Self {
+            mantissa: self.mantissa.div_euclid(pow10(self.scale)?),
+            scale: 0,
+        })
+    }
+
+    pub(crate) fn ceil(self) -> Option<Self> {
+        Some(Self {
+            mantissa: self
+                .mantissa
+                .checked_neg()?
+                .div_euclid(pow10(self.scale)?)
+                .checked_neg()?,
+            scale: 0,
+        })
+    }
+}
+
+impl From<i64> for Decimal {
+    fn from(int: i64) -> Self {
+        Self {
+            mantissa: int.into(),
+            scale: 0,
+        }
+    }
+}
+
+impl PartialEq for Decimal {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for Decimal {}
+
+impl PartialOrd for Decimal {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Decimal {
+    fn cmp(&self, other: &Self) -> Ordering {
+        // Integer parts first, so that the fractional parts can always be rescaled.
+        let (lhs_unit, rhs_unit) = (pow10(self.scale).unwrap(), pow10(other.scale).unwrap());
+        let lhs = (
+            self.mantissa.div_euclid(lhs_unit),
+            self.mantissa.rem_euclid(lhs_unit),
+        );
+        let rhs = (
+            other.mantissa.div_euclid(rhs_unit),
+            other.mantissa.rem_euclid(rhs_unit),
+        );
+
+        let scale = self.scale.max(other.scale);
+        lhs.0.cmp(&rhs.0).then_with(|| {
+            let lhs = lhs.1 * pow10(scale - self.scale).unwrap();
+            let rhs = rhs.1 * pow10(scale - other.scale).unwrap();
+            lhs.cmp(&rhs)
+        })
+    }
+}
+
+impl Display for Decimal {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let digits = self.mantissa.unsigned_abs().to_string();
+        let scale = self.scale as usize;
+        let digits = format!("{digits:0>width$}", width = scale + 1);
+        let (int, fraction) = digits.split_at(digits.len() - scale);
+        if self.mantissa < 0 {
+            write!(f, "-")?;
+        }
+
+        write!(f, "{int}")?;
+        if !fraction.is_empty() {
+            write!(f, ".{fraction}")?;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn decimal(str: &str) -> Decimal {
+        match str.strip_prefix('-') {
+            Some(str) => {
+                let decimal = Decimal::parse(str).unwrap();
+                Decimal::new(-decimal.mantissa, decimal.scale)
+            }
+            None => Decimal::parse(str).unwrap(),
+        }
+    }
+
+    #[test]
+    fn parse() {
+        let parsed = Decimal::parse("12.50").unwrap();
+        assert_eq!((parsed.mantissa, parsed.scale), (1250, 2));
+        assert!(Decimal::parse("-1").is_none());
+        assert!(Decimal::parse(".5").is_none());
+        assert!(Decimal::parse("1.2.3").is_none());
+        assert!(Decimal::parse(&"9".repeat(38)).is_some());
+        assert!(Decimal::parse(&"9".repeat(39)).is_none());
+    }
+
+    #[test]
+    fn div_rounded_half_away_from_zero() {
+        assert_eq!(div_rounded(7, 2), Some(4));
+        assert_eq!(div_rounded(-7, 2), Some(-4));
+        assert_eq!(div_rounded(7, -2), Some(-4));
+        assert_eq!(div_rounded(-7, -2), Some(4));
+        assert_eq!(div_rounded(5, 3), Some(2));
+        assert_eq!(div_rounded(4, 3), Some(1));
+        assert_eq!(div_rounded(-4, 3), Some(-1));
+        assert_eq!(div_rounded(1, 0), None);
+        assert_eq!(div_rounded(i128::MIN, -1), None);
+    }
+
+    #[test]
+    fn negative_rounding() {
+        assert_eq!(decimal("-1.25").round(1), Some(decimal("-1.3")));
+        assert_eq!(decimal("-1.249").round(2), Some(decimal("-1.25")));
+        assert_eq!(decimal("-0.5").round(0), Some(decimal("-1")));
+        assert_eq!(decimal("-0.4").round(0), Some(decimal("0")));
+        assert_eq!(decimal("-1.5").floor(), Some(decimal("-2")));
+        assert_eq!(decimal("-1.5").ceil(), Some(decimal("-1")));
+        assert_eq!(decimal("1.5").floor(), Some(decimal("1")));
+        assert_eq!(decimal("1.5").ceil(), Some(decimal("2")));
+    }
+
+    #[test]
+    fn scale_overflow() {
+        assert!(Decimal::from(1).rescaled(MAX_PRECISION).is_some());
+        assert!(Decimal::from(2).rescaled(MAX_PRECISION).is_none());
+        assert!(Decimal::from(0).rescaled(MAX_PRECISION + 1).is_none());
+
+        // Digits beyond the largest scale get rounded off.
+        let product = Decimal::new(5, 20)
+            .checked_mul(Decimal::new(1, 19))
+            .unwrap();
+        assert_eq!((product.mantissa, product.scale), (1, MAX_PRECISION));
+        let product = Decimal::new(1, 20)
+            .checked_mul(Decimal::new(1, 20))
+            .unwrap();
+        assert_eq!(product, Decimal::from(0));
+
+        assert!(Decimal::new(i128::MAX, 0)
+            .checked_add(Decimal::from(1))
+            .is_none());
+        assert!(Decimal::new(i128::MAX, 0)
+            .checked_add(Decimal::new(1, 1))
+            .is_none());
+    }
+
+    #[test]
+    fn checked_div() {
+        assert_eq!(
+            Decimal::from(1).checked_div(Decimal::from(3)),
+            Some(decimal("0.333333"))
+        );
+        assert_eq!(
+            Decimal::from(2).checked_div(Decimal::from(3)),
+            Some(decimal("0.666667"))
+        );
+        assert_eq!(
+            decimal("-1").checked_div(decimal("0.3")),
+            Some(decimal("-3.3333333"))
+        );
+        assert!(Decimal::from(1).checked_div(Decimal::from(0)).is_none());
+    }
+
+    #[test]
+    fn checked_div_scale_fallback() {
+        // Rescaling 1 to a scale of 38 + 33 fails, the division falls back to a scale of 33.
+        let rhs = Decimal::new(10i128.pow(33), 33);
+        let quotient = Decimal::from(1).checked_div(rhs).unwrap();
+        assert_eq!(quotient, Decimal::from(1));
+        assert_eq!(quotient.scale, MAX_PRECISION);
+    }
+
+    #[test]
+    fn ord_across_scales() {
+        assert_eq!(decimal("1.50"), decimal("1.5"));
+        assert!(decimal("-1.5") < decimal("-1.49"));
+        assert!(decimal("-0.01") < decimal("0"));
+        assert!(decimal("1.01") > decimal("1.009"));
+        assert!(Decimal::new(1, MAX_PRECISION) > Decimal::from(0));
+        assert!(Decimal::new(i128::MAX, 0) > Decimal::new(i128::MAX, MAX_PRECISION));
+        assert!(Decimal::new(i128::MIN, 0) < Decimal::new(i128::MIN, MAX_PRECISION));
+    }
+
+    #[test]
+    fn display() {
+        assert_eq!(decimal("-0.05").to_string(), "-0.05");
+        assert_eq!(decimal("12.50").to_string(), "12.50");
+        assert_eq!(
+            Decimal::new(1, MAX_PRECISION).to_string(),
+            format!("0.{:0>38}", 1)
+        );
+        assert_eq!(decimal("12.50").normalized().to_string(), "12.5");
+    }
+
+    #[test]
+    fn precision() {
+        assert_eq!(decimal("-123.45").precision(), 5);
+        assert_eq!(decimal("0.005").precision(), 3);
+        assert_eq!(Decimal::from(0).precision(), 1);
+    }
+
+    #[test]
+    fn floats() {
+        let from_float = |float: f64| Decimal::from_float(float).map(|decimal| decimal.to_string());
+        assert_eq!(from_float(1.5).as_deref(), Some("1.5"));
+        assert_eq!(from_float(0.1).as_deref(), Some("0.1"));
+        assert_eq!(from_float(-2.25).as_deref(), Some("-2.25"));
+        assert_eq!(from_float(100.0).as_deref(), Some("100"));
+        assert_eq!(from_float(f64::NAN), None);
+        assert_eq!(from_float(f64::INFINITY), None);
+        assert_eq!(from_float(1e300), None);
+        assert_eq!(decimal("-2.25").to_float(), -2.25);
+        assert_eq!(decimal("0.1").to_float(), 0.1);
+    }
+}
@@ -0,0 +1,403 @@
+use parse_display_derive::Display;
+use std::collections::HashMap;
+use terrors::OneOf;
+
+use crate::{
+    database::IntegerOverflowError,
+    datetime::{self, SECONDS_PER_DAY},
+    decimal::{Decimal, MAX_PRECISION},
+    Ty, Value,
+};
+
+#[derive(Debug, Display, Clone)]
+pub enum FunctionCallError {
+    #[display("Function \"{0}\" does not exist.")]
+    NoSuchFunction(String),
+    #[display("Function \"{function}\" cannot take {count} arguments.")]
+    IncorrectArgumentCount { function: String, count: usize },
+    #[display("Argument {index} of function \"{function}\" cannot be of type: {ty}.")]
+    IncorrectArgumentTy {
+        function: String,
+        index: usize,
+        ty: Ty,
+    },
+    #[display("Function \"{function}\" is expected to return a type: {ty}.")]
+    IncorrectReturnTy { function: String, ty: Ty },
+    #[display("Aggregate function \"{0}\" can only be called directly in a selection.")]
+    MisplacedAggregate(String),
+    #[display("Cannot select aggregated and non aggregated values together.")]
+    MixedAggregate,
+    #[display("Function \"unnest\" can only be called directly in a selection.")]
+    MisplacedUnnest,
+}
+
+/// Types of the parameters and result of a registered function, arguments and results get
+/// converted to them like values stored in columns of those types.
+#[derive(Debug, Clone)]
+pub struct Signature {
+    pub parameters: Vec<Ty>,
+    pub returns: Ty,
+}
+
+impl Signature {
+    pub fn new(parameters: impl Into<Vec<Ty>>, returns: Ty) -> Self {
+        Self {
+            parameters: parameters.into(),
+            returns,
+        }
+    }
+}
+
+type Body = Box<dyn Fn(&[Value]) -> Value>;
+
+/// Functions registered by the host, looked up before the built-in ones.
+#[derive(Default)]
+pub struct Functions {
+    functions: HashMap<String, (Signature, Body)>,
+}
+
+impl Functions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn register(
+        &mut self,
+        identifier: impl Into<String>,
+        signature: Signature,
+        function: impl Fn(&[Value]) -> Value + 'static,
+    ) {
+        self.functions
+            .insert(identifier.into(), (signature, Box::new(function)));
+    }
+
+    pub(crate) fn is_registered(&self, identifier: &str) -> bool {
+        self.functions.contains_key(identifier)
+    }
+
+    /// Calls a registered function or a built-in one. A registered function returns nil
+    /// without being called when any of its arguments is nil.
+    pub(crate) fn call(
+        &self,
+        identifier: &str,
+        arguments: Vec<Value>,
+    ) -> Result<Value, OneOf<(FunctionCallError, IntegerOverflowError)>> {
+        let Some((signature, body)) = self.functions.get(identifier) else {
+            return call_builtin(identifier, arguments);
+        };
+
+        if arguments.len() != signature.parameters.len() {
+            return Err(OneOf::new(FunctionCallError::IncorrectArgumentCount {
+                function: identifier.to_owned(),
+                count: arguments.len(),
+            }));
+        }
+
+        // Arguments get converted to the parameter types like values stored in columns.
+        let arguments = arguments
+            .into_iter()
+            .zip(&signature.parameters)
+            .enumerate()
+            .map(|(index, (argument, ty))| {
+                let argument_ty = argument.ty();
+                match argument {
+                    Value::Nil => Some(Value::Nil),
+                    argument => argument.coerced(ty),
+                }
+                .ok_or_else(|| FunctionCallError::IncorrectArgumentTy {
+                    function: identifier.to_owned(),
+                    index,
+                    ty: argument_ty,
+                })
+            })
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(OneOf::new)?;
+
+        if arguments
+            .iter()
+            .any(|argument| matches!(argument, Value::Nil))
+        {
+            return Ok(Value::Nil);
+        }
+
+        match body(&arguments) {
+            Value::Nil => Ok(Value::Nil),
+            value => value.coerced(&signature.returns).ok_or_else(|| {
+                OneOf::new(FunctionCallError::IncorrectReturnTy {
+                    function: identifier.to_owned(),
+                    ty: signature.returns.clone(),
+                })
+            }),
+        }
+    }
+}
+
+/// Kinds of types accepted by each parameter of a built-in function, an empty list accepts any
+/// type. Decimals and lists stand for those of any precision, scale and element type. The last
+/// parameter of a variadic function applies to all the remaining arguments.
+fn parameters(identifier: &str) -> Option<Vec<Vec<Ty>>> {
+    Some(match identifier {
+        "upper" | "lower" | "trim" => vec![vec![Ty::Str]],
+        "length" => vec![vec![Ty::Str, Ty::Bytes, Ty::any_list()]],
+        "contains" => vec![vec![Ty::any_list()], vec![]],
+        "substr" => vec![vec![Ty::Str], vec![Ty::Int], vec![Ty::Int]],
+        "abs" | "floor" | "ceil" => vec![vec![Ty::Int, Ty::Float, Ty::DECIMAL]],
+        "round" => vec![vec![Ty::Int, Ty::Float, Ty::DECIMAL], vec![Ty::Int]],
+        "coalesce" => vec![vec![]],
+        "if" => vec![vec![Ty::Bool], vec![], vec![]],
+        "year" | "month" | "day" => vec![vec![Ty::Date, Ty::Timestamp]],
+        "hour" | "minute" | "second" => vec![vec![Ty::Time, Ty::Timestamp]],
+        "now" => vec![],
+        _ => return None,
+    })
+}
+
+fn check_arguments(
+    identifier: &str,
+    parameters: &[&[Ty]],
+    arguments: &[Value],
+) -> Result<(), FunctionCallError> {
+    for (index, argument) in arguments.iter().enumerate() {
+        let Some(tys) = parameters.get(index).or(parameters.last()) else {
+            break;
+        };
+
+        if argument.ty() != Ty::Nil
+            && !tys.is_empty()
+            && !tys.iter().any(|ty| ty.is(&argument.ty()))
+        {
+            return Err(FunctionCallError::IncorrectArgumentTy {
+                function: identifier.to_owned(),
+                index,
+                ty: argument.ty(),
+            });
+        }
+    }
+
+    Ok(())
+}
+
+/// Calls a built-in function. Apart from `coalesce` and `if`, functions return nil
+/// when any of their arguments is nil.
+fn call_builtin(
+    identifier: &str,
+    arguments: Vec<Value>,
+) -> Result<Value, OneOf<(FunctionCallError, IntegerOverflowError)>> {
+    if is_aggregate(identifier) {
+        return Err(OneOf::new(FunctionCallError::MisplacedAggregate(
+            identifier.to_owned(),
+        )));
+    }
+
+    if is_unnest(identifier) {
+        return Err(OneOf::new(FunctionCallError::MisplacedUnnest));
+    }
+
+    let Some(parameters) = parameters(identifier) else {
+        return Err(OneOf::new(FunctionCallError::NoSuchFunction(
+            identifier.to_owned(),
+        )));
+    };
+
+    let arity = match identifier {
+        "substr" | "round" => parameters.len() - 1..=parameters.len(),
+        "coalesce" => 1..=usize::MAX,
+        _ => parameters.len()..=parameters.len(),
+    };
+
+    if !arity.contains(&arguments.len()) {
+        return Err(OneOf::new(FunctionCallError::IncorrectArgumentCount {
+            function: identifier.to_owned(),
+            count: arguments.len(),
+        }));
+    }
+
+    let parameters = parameters.iter().map(Vec::as_slice).collect::<Vec<_>>();
+    check_arguments(identifier, &parameters, &arguments).map_err(OneOf::new)?;
+    match (identifier, arguments.as_slice()) {
+        ("coalesce", _) => {
+            return Ok(arguments
+                .into_iter()
+                .find(|argument| !matches!(argument, Value::Nil))
+                .unwrap_or(Value::Nil));
+        }
+        ("if", [condition, then, otherwise]) => {
+            return Ok(match condition {
+                Value::Bool(true) => then.clone(),
+                _ => otherwise.clone(),
+            });
+        }
+        _ => {}
+    }
+
+    if arguments
+        .iter()
+        .any(|argument| matches!(argument, Value::Nil))
+    {
+        return Ok(Value::Nil);
+    }
+
+    Ok(match (identifier, arguments.as_slice()) {
+        ("upper", [Value::Str(str)]) => Value::Str(str.to_uppercase()),
+        ("lower", [Value::Str(str)]) => Value::Str(str.to_lowercase()),
+        ("length", [Value::Str(str)]) => Value::Int(str.chars().count() as i64),
+        ("length", [Value::Bytes(bytes)]) => Value::Int(bytes.len() as i64),
+        ("length", [Value::List(values)]) => Value::Int(values.len() as i64),
+        // Compares like "==", so the database evaluates it like "in".
+        ("contains", _) => unreachable!("evaluated by the database"),
+        ("trim", [Value::Str(str)]) => Value::Str(str.trim().to_owned()),
+        ("substr", [Value::Str(str), Value::Int(start), rest @ ..]) => {
+            let chars = str.chars().skip((*start).max(0) as usize);
+            Value::Str(match rest {
+                [Value::Int(length)] => chars.take((*length).max(0) as usize).collect(),
+                _ => chars.collect(),
+            })
+        }
+        ("abs", [Value::Int(int)]) => Value::Int(
+            int.checked_abs()
+                .ok_or_else(|| OneOf::new(IntegerOverflowError))?,
+        ),
+        ("abs", [Value::Float(float)]) => Value::Float(float.abs()),
+        ("round" | "floor" | "ceil", [Value::Int(int)]) => Value::Int(*int),
+        ("round", [Value::Int(int), Value::Int(_)]) => Value::Int(*int),
+        ("round", [Value::Float(float)]) => Value::Float(float.round()),
+        ("round", [Value::Float(float), Value::Int(digits)]) => {
+            let unit = 10f64.powi((*digits).clamp(i32::MIN as i64, i32::MAX as i64) as i32);
+            Value::Float((float * unit).round() / unit)
+        }
+        ("abs", [Value::Decimal(decimal)]) => Value::Decimal(checked(decimal.abs())?),
+        ("round", [Value::Decimal(decimal)]) => Value::Decimal(checked(decimal.round(0))?),
+        ("round", [Value::Decimal(decimal), Value::Int(digits)]) => {
+            let digits = (*digits).clamp(0, MAX_PRECISION as i64) as u8;
+            Value::Decimal(checked(decimal.round(digits))?)
+        }
+        ("floor", [Value::Decimal(decimal)]) => Value::Decimal(checked(decimal.floor())?),
+        ("ceil", [Value::Decimal(decimal)]) => Value::Decimal(checked(decimal.ceil())?),
+        ("floor", [Value::Float(float)]) => Value::Float(float.floor()),
+        ("ceil", [Value::Float(float)]) => Value::Float(float.ceil()),
+        ("year" | "month" | "day", [Value::Date(days)]) => date_part(identifier, *days),
+        ("year" | "month" | "day", [Value::Timestamp(seconds)]) => {
+            date_part(identifier, seconds.div_euclid(SECONDS_PER_DAY))
+        }
+        ("hour" | "minute" | "second", [Value::Time(seconds)]) => time_part(identifier, *seconds),
+        ("hour" | "minute" | "second", [Value::Timestamp(seconds)]) => {
+            time_part(identifier, seconds.rem_euclid(SECONDS_PER_DAY))
+        }
+        ("now", []) => Value::Timestamp(datetime::now()),
+        // Arguments are checked against the parameters above.
+        _ => unreachable!(),
+    })
+}
+
+fn checked<T>(value: Option<T>) -> Result<T, OneOf<(FunctionCallError, IntegerOverflowError)>> {
+    value.ok_or_else(|| OneOf::new(IntegerOverflowError))
+}
+
+fn date_part(identifier: &str, days: i64) -> Value {
+    let (year, month, day) = datetime::civil_from_days(days);
+    Value::Int(match identifier {
+        "year" => year,
+        "month" => month,
+        _ => day,
+    })
+}
+
+fn time_part(identifier: &str, seconds: i64) -> Value {
+    Value::Int(match identifier {
+        "hour" => seconds / 3600,
+        "minute" => seconds / 60 % 60,
+        _ => seconds % 60,
+    })
+}
+
+/// Whether `identifier` is an aggregate function, which gets called with the values of all
+/// the selected rows.
+pub(crate) fn is_aggregate(identifier: &str) -> bool {
+    matches!(identifier, "count" | "sum" | "avg" | "min" | "max")
+}
+
+/// Whether `identifier` is `unnest`, which expands a list into a row for each of its elements.
+pub(crate) fn is_unnest(identifier: &str) -> bool {
+    identifier == "unnest"
+}
+
+/// Calls an aggregate function, nil values are skipped. Aggregating no values results in nil,
+/// apart from `count`.
+pub(crate) fn aggregate(
+    identifier: &str,
+    values: Vec<Value>,
+) -> Result<Value, OneOf<(FunctionCallError, IntegerOverflowError)>> {
+    let tys: &[Ty] = match identifier {
+        "count" => {
+            return Ok(Value::Int(
+                values.iter().filter(|value| value.ty() != Ty::Nil).count() as i64,
+            ))
+        }
+        "sum" | "avg" => &[Ty::Int, Ty::Float, Ty::DECIMAL],
+        "min" | "max" => &[
+            Ty::Int,
+            Ty::Float,
+            Ty::Str,
+            Ty::DECIMAL,
+            Ty::Date,
+            Ty::Time,
+            Ty::Timestamp,
+            Ty::Interval,
+        ],
+        _ => unreachable!("not an aggregate function"),
+    };
+
+    let mut values = values.into_iter().filter(|value| value.ty() != Ty::Nil);
+    let Some(mut result) = values.next() else {
+        return Ok(Value::Nil);
+    };
+
+    check_arguments(identifier, &[tys], std::slice::from_ref(&result)).map_err(OneOf::new)?;
+    let mut count = 1;
+    for value in values {
+        count += 1;
+        result = match (identifier, result, value) {
+            ("sum" | "avg", Value::Int(lhs), Value::Int(rhs)) => Value::Int(
+                lhs.checked_add(rhs)
+                    .ok_or_else(|| OneOf::new(IntegerOverflowError))?,
+            ),
+            ("sum" | "avg", Value::Float(lhs), Value::Float(rhs)) => Value::Float(lhs + rhs),
+            ("sum" | "avg", Value::Decimal(lhs), Value::Decimal(rhs)) => {
+                Value::Decimal(checked(lhs.checked_add(rhs))?)
+            }
+            ("min", Value::Decimal(lhs), Value::Decimal(rhs)) => Value::Decimal(lhs.min(rhs)),
+            ("max", Value::Decimal(lhs), Value::Decimal(rhs)) => Value::Decimal(lhs.max(rhs)),
+            ("min", Value::Int(lhs), Value::Int(rhs)) => Value::Int(lhs.min(rhs)),
+            ("min", Value::Float(lhs), Value::Float(rhs)) => Value::Float(lhs.min(rhs)),
+            ("min", Value::Str(lhs), Value::Str(rhs)) => Value::Str(lhs.min(rhs)),
+            ("max", Value::Int(lhs), Value::Int(rhs)) => Value::Int(lhs.max(rhs)),
+            ("max", Value::Float(lhs), Value::Float(rhs)) => Value::Float(lhs.max(rhs)),
+            ("max", Value::Str(lhs), Value::Str(rhs)) => Value::Str(lhs.max(rhs)),
+            ("min", Value::Date(lhs), Value::Date(rhs)) => Value::Date(lhs.min(rhs)),
+            ("min", Value::Time(lhs), Value::Time(rhs)) => Value::Time(lhs.min(rhs)),
+            ("min", Value::Timestamp(lhs), Value::Timestamp(rhs)) => Value::Timestamp(lhs.min(rhs)),
+            ("min", Value::Interval(lhs), Value::Interval(rhs)) => Value::Interval(lhs.min(rhs)),
+            ("max", Value::Date(lhs), Value::Date(rhs)) => Value::Date(lhs.max(rhs)),
+            ("max", Value::Time(lhs), Value::Time(rhs)) => Value::Time(lhs.max(rhs)),
+            ("max", Value::Timestamp(lhs), Value::Timestamp(rhs)) => Value::Timestamp(lhs.max(rhs)),
+            ("max", Value::Interval(lhs), Value::Interval(rhs)) => Value::Interval(lhs.max(rhs)),
+            // All values need to be of the same type.
+            (_, _, value) => {
+                return Err(OneOf::new(FunctionCallError::IncorrectArgumentTy {
+                    function: identifier.to_owned(),
+                    index: 0,
+                    ty: value.ty(),
+                }))
+            }
+        };
+    }
+
+    Ok(match (identifier, result) {
+        ("avg", Value::Int(sum)) => Value::Float(sum as f64 / count as f64),
+        ("avg", Value::Float(sum)) => Value::Float(sum / count as f64),
+        ("avg", Value::Decimal(sum)) => {
+            Value::Decimal(checked(sum.checked_div(Decimal::from(count as i64)))?)
+        }
+        (_, result) => result,
+    })
+}
@@ -1,4 +1,5 @@
 use super::token::{Keyword, Token};
+use crate::decimal::Decimal;
 use parse_display_derive::Display;
 use std::{ops::Range, str::FromStr};
 
@@ -16,6 +17,12 @@ pub enum TokenizeError {
     InvalidFloatLiteral,
     #[display("Invalid int literal.")]
     InvalidIntLiteral,
+    #[display("Invalid decimal literal.")]
+    InvalidDecimalLiteral,
+    #[display("Invalid escape sequence in str literal.")]
+    InvalidEscape,
+    #[display("Invalid bytes literal, expected pairs of hex digits.")]
+    InvalidBytesLiteral,
 }
 
 #[derive(Debug, Clone, Copy)]
@@ -28,7 +35,7 @@ pub struct TokenIter<'a> {
 impl<'a> TokenIter<'a> {
     pub fn new(bytes: &'a [u8]) -> Self {
         Self {
-            bytes: bytes.as_ref(),
+            bytes,
             last_index: 0,
             index: 0,
         }
@@ -56,6 +63,59 @@ impl<'a> TokenIter<'a> {
         self.bytes.get(self.index).copied()
     }
 
+    /// Parses the `{...}` part of a `\u{...}` escape sequence.
+    fn next_unicode_escape(&mut self) -> std::result::Result<char, TokenizeError> {
+        let Some(b'{') = self.next_byte() else {
+            return Err(TokenizeError::InvalidEscape);
+        };
+
+        let mut code = 0u32;
+        let mut digits = 0;
+        loop {
+            let digit = match self.next_byte() {
+                Some(b'}') if digits > 0 => break,
+                Some(byte @ b'0'..=b'9') => byte - b'0',
+                Some(byte @ b'a'..=b'f') => byte - b'a' + 10,
+                Some(byte @ b'A'..=b'F') => byte - b'A' + 10,
+                _ => return Err(TokenizeError::InvalidEscape),
+            };
+
+            digits += 1;
+            if digits > 6 {
+                return Err(TokenizeError::InvalidEscape);
+            }
+
+            code = code * 16 + digit as u32;
+        }
+
+        char::from_u32(code).ok_or(TokenizeError::InvalidEscape)
+    }
+
+    /// Parses the `"..."` part of a `x"..."` bytes literal.
+    fn next_bytes(&mut self) -> Result {
+        let Some(b'"') = self.next_byte() else {
+            return Err(TokenizeError::InvalidBytesLiteral);
+        };
+
+        let mut bytes = Vec::new();
+        let mut high = None;
+        loop {
+            let digit = match self.next_byte() {
+                Some(b'"') if high.is_none() => return Ok(Token::BytesLiteral(bytes)),
+                Some(byte @ b'0'..=b'9') => byte - b'0',
+                Some(byte @ b'a'..=b'f') => byte - b'a' + 10,
+                Some(byte @ b'A'..=b'F') => byte - b'A' + 10,
+                Some(_) => return Err(TokenizeError::InvalidBytesLiteral),
+                None => return Err(TokenizeError::NonTerminatedStr),
+            };
+
+            match high.take() {
+                Some(high) => bytes.push(high << 4 | digit),
+                None => high = Some(digit),
+            }
+        }
+    }
+
     fn next_token(&mut self, mut byte: u8) -> Result {
         match byte {
             b'"' => {
@@ -69,6 +129,18 @@ impl<'a> TokenIter<'a> {
 
                             return Ok(Token::StrLiteral(str));
                         }
+                        b'\\' => match self.next_byte() {
+                            Some(b'"') => bytes.push(b'"'),
+                            Some(b'\\') => bytes.push(b'\\'),
+                            Some(b'n') => bytes.push(b'\n'),
+                            Some(b't') => bytes.push(b'\t'),
+                            Some(b'u') => {
+                                let char = self.next_unicode_escape()?;
+                                bytes.extend_from_slice(char.encode_utf8(&mut [0; 4]).as_bytes());
+                            }
+                            Some(_) => return Err(TokenizeError::InvalidEscape),
+                            None => break,
+                        },
                         byte => bytes.push(byte),
                     }
                 }
@@ -89,6 +161,18 @@ impl<'a> TokenIter<'a> {
                     bytes.push(byte);
                 }
 
+                // A `d` suffix makes a decimal, like `12.50d`.
+                if self.next_byte_if(|byte| byte == b'd').is_some() {
+                    // It can only have utf-8 bytes because of the code above.
+                    let Some(decimal) =
+                        Decimal::parse(unsafe { std::str::from_utf8_unchecked(&bytes) })
+                    else {
+                        return Err(TokenizeError::InvalidDecimalLiteral);
+                    };
+
+                    return Ok(Token::DecimalLiteral(decimal));
+                }
+
                 if dot {
                     // It can only have utf-8 bytes because of the code above.
                     let Ok(float) = unsafe { std::str::from_utf8_unchecked(&bytes) }.parse() else {
@@ -104,6 +188,10 @@ impl<'a> TokenIter<'a> {
                 Ok(Token::IntLiteral(int))
             }
             b',' => Ok(Token::Comma),
+            b':' if self.peek_byte() == Some(b':') => {
+                _ = self.next_byte();
+                Ok(Token::DoubleColon)
+            }
             b':' => Ok(Token::Colon),
             b';' => Ok(Token::SemiColon),
             b'@' => Ok(Token::At),
@@ -111,6 +199,9 @@ impl<'a> TokenIter<'a> {
             b')' => Ok(Token::RightSmooth),
             b'{' => Ok(Token::LeftCurly),
             b'}' => Ok(Token::RightCurly),
+            b'[' => Ok(Token::LeftSquare),
+            b']' => Ok(Token::RightSquare),
+            b'.' => Ok(Token::Dot),
             b'?' => Ok(Token::QuestionMark),
             b'+' => Ok(Token::Plus),
             b'-' => Ok(Token::Minus),
@@ -130,13 +221,13 @@ impl<'a> TokenIter<'a> {
 
                 Ok(Token::DoublePipe)
             }
-            b'=' => {
-                let Some(b'=') = self.next_byte() else {
-                    return Err(TokenizeError::UnexpectedCharacter);
-                };
-
-                Ok(Token::DoubleEq)
-            }
+            b'=' => match self.peek_byte() {
+                Some(b'=') => {
+                    _ = self.next_byte();
+                    Ok(Token::DoubleEq)
+                }
+                _ => Ok(Token::Eq),
+            },
             b'<' => match self.peek_byte() {
                 Some(b'=') => {
                     _ = self.next_byte();
@@ -151,6 +242,7 @@ impl<'a> TokenIter<'a> {
                 }
                 _ => Ok(Token::More),
             },
+            b'x' if self.peek_byte() == Some(b'"') => self.next_bytes(),
             b'A'..=b'Z' | b'a'..=b'z' | b'_' | 128.. => {
                 let mut bytes = vec![byte];
                 loop {
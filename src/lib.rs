@@ -1,25 +1,83 @@
-use std::fmt::Display;
+use std::{
+    collections::BTreeMap,
+    fmt::Display,
+    hash::{Hash, Hasher},
+    rc::Rc,
+};
+
+use decimal::{Decimal, MAX_PRECISION};
 
 pub mod prelude;
 
+mod cast;
 mod command;
 mod database;
+mod datetime;
+mod decimal;
+mod function;
 mod lexer;
 mod macros;
 mod parser;
 mod token;
 
-#[derive(Debug, Clone, Copy, PartialEq)]
+#[derive(Debug, Clone, PartialEq)]
 pub enum Ty {
     Str,
     Int,
     Float,
     Bool,
+    Bytes,
+    Decimal {
+        precision: u8,
+        scale: u8,
+    },
+    Date,
+    Time,
+    Timestamp,
+    Interval,
+    Enum(Rc<EnumTy>),
+    /// Lists of values of the element type, lists of nil are lists of values of an unknown type.
+    List(Box<Ty>),
+    /// Documents of fields holding values of any type.
+    Doc,
     Nil,
 }
 
+/// A type declared with `new type`, whose values are one of its variants.
+#[derive(Debug, PartialEq)]
+pub struct EnumTy {
+    pub identifier: String,
+    pub variants: Vec<String>,
+}
+
+impl Ty {
+    /// Stands for decimals of any precision and scale where only the kind of type matters.
+    pub(crate) const DECIMAL: Ty = Ty::Decimal {
+        precision: MAX_PRECISION,
+        scale: 0,
+    };
+
+    /// Stands for lists of any type where only the kind of type matters.
+    pub(crate) fn any_list() -> Ty {
+        Ty::List(Box::new(Ty::Nil))
+    }
+
+    /// Whether both are the same kind of type, ignoring the precision and scale of decimals and
+    /// the element type of lists.
+    pub(crate) fn is(&self, other: &Ty) -> bool {
+        std::mem::discriminant(self) == std::mem::discriminant(other)
+    }
+}
+
 impl Display for Ty {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Ty::Decimal { precision, scale } => return write!(f, "decimal({precision}, {scale})"),
+            Ty::Enum(enum_ty) => return write!(f, "{}", enum_ty.identifier),
+            Ty::List(ty) => return write!(f, "[{ty}]"),
+            _ => {}
+        }
+
         write!(
             f,
             "{}",
@@ -29,6 +87,13 @@ impl Display for Ty {
                 Ty::Float => "float",
                 Ty::Nil => "nil",
                 Ty::Bool => "bool",
+                Ty::Bytes => "bytes",
+                Ty::Decimal { .. } | Ty::Enum(_) | Ty::List(_) => unreachable!(),
+                Ty::Date => "date",
+                Ty::Time => "time",
+                Ty::Timestamp => "timestamp",
+                Ty::Interval => "interval",
+                Ty::Doc => "doc",
             }
         )
     }
@@ -37,9 +102,24 @@ impl Display for Ty {
 #[derive(Debug, Clone)]
 pub enum Value {
     Str(String),
-    Int(i32),
-    Float(f32),
+    Int(i64),
+    Float(f64),
     Bool(bool),
+    Bytes(Vec<u8>),
+    Decimal(Decimal),
+    /// Days since 1970-01-01.
+    Date(i64),
+    /// Seconds since midnight.
+    Time(i64),
+    /// Seconds since 1970-01-01T00:00:00.
+    Timestamp(i64),
+    /// A number of seconds.
+    Interval(i64),
+    /// The index of a variant of the type.
+    Enum(Rc<EnumTy>, u32),
+    List(Vec<Value>),
+    /// Fields of a document, which can be documents or lists themselves.
+    Doc(BTreeMap<String, Value>),
     Nil,
 }
 
@@ -51,18 +131,223 @@ impl Value {
             Value::Int(_) => Ty::Int,
             Value::Float(_) => Ty::Float,
             Value::Bool(_) => Ty::Bool,
+            Value::Bytes(_) => Ty::Bytes,
+            Value::Decimal(decimal) => Ty::Decimal {
+                precision: decimal.precision(),
+                scale: decimal.scale(),
+            },
+            Value::Date(_) => Ty::Date,
+            Value::Time(_) => Ty::Time,
+            Value::Timestamp(_) => Ty::Timestamp,
+            Value::Interval(_) => Ty::Interval,
+            Value::Enum(enum_ty, _) => Ty::Enum(enum_ty.clone()),
+            Value::Doc(_) => Ty::Doc,
+            Value::List(values) => Ty::List(Box::new(
+                values
+                    .iter()
+                    .map(Value::ty)
+                    .find(|ty| *ty != Ty::Nil)
+                    .unwrap_or(Ty::Nil),
+            )),
+        }
+    }
+
+    /// Converts a value to be stored as type `ty`, decimals get rounded to its scale, strings
+    /// become enum variants and elements of lists get converted to the element type. `None` if
+    /// the value does not fit.
+    pub(crate) fn conformed(self, ty: &Ty) -> Option<Value> {
+        match (self, ty) {
+            (Value::Decimal(decimal), Ty::Decimal { precision, scale }) => {
+                let decimal = decimal.rescaled(*scale)?;
+                (decimal.precision() <= *precision).then_some(Value::Decimal(decimal))
+            }
+            (Value::Str(str), Ty::Enum(enum_ty)) => {
+                let index = enum_ty
+                    .variants
+                    .iter()
+                    .position(|variant| *variant == str)?;
+                Some(Value::Enum(enum_ty.clone(), index as u32))
+            }
+            (Value::List(values), Ty::List(ty)) => values
+                .into_iter()
+                .map(|value| match value {
+                    Value::Nil => Some(Value::Nil),
+                    value => value.conformed(ty),
+                })
+                .collect::<Option<_>>()
+                .map(Value::List),
+            (value, ty) if value.ty() == *ty => Some(value),
+            _ => None,
         }
     }
+
+    /// Converts a value like `conformed`, also converting numbers to other types when nothing
+    /// gets lost: ints to floats and decimals, floats to decimals and integral floats to ints.
+    pub(crate) fn coerced(self, ty: &Ty) -> Option<Value> {
+        match (self, ty) {
+            (Value::Int(int), Ty::Float) => Some(Value::Float(int as f64)),
+            (Value::Int(int), Ty::Decimal { .. }) => {
+                Value::Decimal(Decimal::from(int)).conformed(ty)
+            }
+            // Only when no digits of its shortest form get rounded away.
+            (Value::Float(float), Ty::Decimal { scale, .. }) => {
+                let decimal = Decimal::from_float(float)?.normalized();
+                (decimal.scale() <= *scale)
+                    .then_some(Value::Decimal(decimal))?
+                    .conformed(ty)
+            }
+            (Value::Float(float), Ty::Int)
+                if float.fract() == 0.0 && float >= i64::MIN as f64 && float < i64::MAX as f64 =>
+            {
+                Some(Value::Int(float as i64))
+            }
+            (Value::List(values), Ty::List(ty)) => values
+                .into_iter()
+                .map(|value| match value {
+                    Value::Nil => Some(Value::Nil),
+                    value => value.coerced(ty),
+                })
+                .collect::<Option<_>>()
+                .map(Value::List),
+            (value, ty) => value.conformed(ty),
+        }
+    }
+
+    /// The name of the variant of an enum value.
+    ///
+    /// # Panics
+    /// if the value is not an enum.
+    pub(crate) fn variant(&self) -> &str {
+        let Value::Enum(enum_ty, index) = self else {
+            panic!("not an enum");
+        };
+
+        &enum_ty.variants[*index as usize]
+    }
+}
+
+/// Equality for telling values apart (used by "distinct"), unlike comparisons in expressions
+/// nil equals nil and floats equal themselves, even NaN.
+impl PartialEq for Value {
+    fn eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (Value::Str(lhs), Value::Str(rhs)) => lhs == rhs,
+            (Value::Int(lhs), Value::Int(rhs)) => lhs == rhs,
+            (Value::Float(lhs), Value::Float(rhs)) => float_bits(*lhs) == float_bits(*rhs),
+            (Value::Bool(lhs), Value::Bool(rhs)) => lhs == rhs,
+            (Value::Bytes(lhs), Value::Bytes(rhs)) => lhs == rhs,
+            (Value::Decimal(lhs), Value::Decimal(rhs)) => lhs == rhs,
+            (Value::Enum(lhs_ty, lhs), Value::Enum(rhs_ty, rhs)) => lhs_ty == rhs_ty && lhs == rhs,
+            (Value::List(lhs), Value::List(rhs)) => lhs == rhs,
+            (Value::Doc(lhs), Value::Doc(rhs)) => lhs == rhs,
+            (Value::Date(lhs), Value::Date(rhs))
+            | (Value::Time(lhs), Value::Time(rhs))
+            | (Value::Timestamp(lhs), Value::Timestamp(rhs))
+            | (Value::Interval(lhs), Value::Interval(rhs)) => lhs == rhs,
+            (Value::Nil, Value::Nil) => true,
+            _ => false,
+        }
+    }
+}
+
+impl Eq for Value {}
+
+impl Hash for Value {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        std::mem::discriminant(self).hash(state);
+        match self {
+            Value::Str(str) => str.hash(state),
+            Value::Int(int) => int.hash(state),
+            Value::Float(float) => float_bits(*float).hash(state),
+            Value::Bool(bool) => bool.hash(state),
+            Value::Bytes(bytes) => bytes.hash(state),
+            Value::Enum(enum_ty, index) => (&enum_ty.identifier, index).hash(state),
+            Value::List(values) => values.hash(state),
+            Value::Doc(fields) => fields.hash(state),
+            Value::Decimal(decimal) => {
+                // Equal decimals can have different scales.
+                let decimal = decimal.normalized();
+                (decimal.mantissa(), decimal.scale()).hash(state);
+            }
+            Value::Date(int) | Value::Time(int) | Value::Timestamp(int) | Value::Interval(int) => {
+                int.hash(state)
+            }
+            Value::Nil => {}
+        }
+    }
+}
+
+/// Bits of a float with all zeros and NaNs made the same.
+fn float_bits(float: f64) -> u64 {
+    if float == 0.0 {
+        0.0f64.to_bits()
+    } else if float.is_nan() {
+        f64::NAN.to_bits()
+    } else {
+        float.to_bits()
+    }
 }
 
 impl Display for Value {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
-            Value::Str(str) => write!(f, "\"{str}\""),
+            Value::Str(str) => {
+                write!(f, "\"")?;
+                for char in str.chars() {
+                    match char {
+                        '"' => write!(f, "\\\"")?,
+                        '\\' => write!(f, "\\\\")?,
+                        '\n' => write!(f, "\\n")?,
+                        '\t' => write!(f, "\\t")?,
+                        char if char.is_control() => write!(f, "\\u{{{:x}}}", char as u32)?,
+                        char => write!(f, "{char}")?,
+                    }
+                }
+
+                write!(f, "\"")
+            }
             Value::Nil => write!(f, "nil"),
             Value::Int(int) => write!(f, "{int}"),
             Value::Float(float) => write!(f, "{float}"),
             Value::Bool(bool) => write!(f, "{bool}"),
+            Value::Decimal(decimal) => write!(f, "{decimal}"),
+            Value::Enum(..) => write!(f, "{}", Value::Str(self.variant().to_owned())),
+            Value::List(values) => {
+                write!(f, "[")?;
+                for (index, value) in values.iter().enumerate() {
+                    if index > 0 {
+                        write!(f, ", ")?;
+                    }
+
+                    write!(f, "{value}")?;
+                }
+
+                write!(f, "]")
+            }
+            Value::Doc(fields) => {
+                write!(f, "{{")?;
+                for (index, (key, value)) in fields.iter().enumerate() {
+                    if index > 0 {
+                        write!(f, ", ")?;
+                    }
+
+                    write!(f, "{}: {value}", Value::Str(key.clone()))?;
+                }
+
+                write!(f, "}}")
+            }
+            Value::Bytes(bytes) => {
+                write!(f, "x\"")?;
+                for byte in bytes {
+                    write!(f, "{byte:02x}")?;
+                }
+
+                write!(f, "\"")
+            }
+            Value::Date(days) => datetime::fmt_date(f, *days),
+            Value::Time(seconds) => datetime::fmt_time(f, *seconds),
+            Value::Timestamp(seconds) => datetime::fmt_timestamp(f, *seconds),
+            Value::Interval(seconds) => datetime::fmt_interval(f, *seconds),
         }
     }
 }
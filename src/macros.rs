@@ -43,7 +43,7 @@ macro_rules! operator {
         }
 
         impl Operator {
-            fn precedence(&self) -> u8 {
+            pub(crate) fn precedence(&self) -> u8 {
                 match self {
                     $($(Self::$ident)|* => $precedence,)*
                 }
@@ -1,12 +1,23 @@
 use super::{
-    command::{ColumnDefinition, Command, Expression, Selection},
+    command::{
+        ColumnDefinition, Command, Expression, Key, OnRemove, Reference, Selection, SetOperation,
+        TyDefinition,
+    },
     lexer::{self, TokenIter, TokenizeError},
     token::{Keyword, Token},
     Value,
 };
-use crate::{command::Operator, Ty};
+use crate::{
+    command::Operator,
+    datetime,
+    decimal::{Decimal, MAX_PRECISION},
+    Ty,
+};
 use parse_display_derive::Display;
-use std::{collections::HashMap, ops::Range};
+use std::{
+    collections::{BTreeMap, HashMap},
+    ops::Range,
+};
 
 #[derive(Debug, Display, Clone)]
 pub enum ParseError {
@@ -16,6 +27,10 @@ pub enum ParseError {
     ExpectedToken(String),
     #[display("No such row attribute.")]
     NoSuchRowAttribute,
+    #[display("Invalid {ty} literal: \"{literal}\".")]
+    InvalidLiteral { ty: Ty, literal: String },
+    #[display("Decimal precision needs to be from 1 to 38, with a scale no larger than it.")]
+    InvalidPrecision,
 }
 
 impl From<TokenizeError> for ParseError {
@@ -56,6 +71,8 @@ impl<'a> CommandIter<'a> {
             Some(Ok(Token::StrLiteral(str))) => Value::Str(str.clone()),
             Some(Ok(Token::IntLiteral(int))) => Value::Int(*int),
             Some(Ok(Token::FloatLiteral(float))) => Value::Float(*float),
+            Some(Ok(Token::BytesLiteral(bytes))) => Value::Bytes(bytes.clone()),
+            Some(Ok(Token::DecimalLiteral(decimal))) => Value::Decimal(*decimal),
             Some(Ok(Token::Keyword(Keyword::Nil))) => Value::Nil,
             Some(Ok(Token::Keyword(Keyword::True))) => Value::Bool(true),
             Some(Ok(Token::Keyword(Keyword::False))) => Value::Bool(false),
@@ -63,17 +80,263 @@ impl<'a> CommandIter<'a> {
         })
     }
 
+    fn peek_operator(&mut self) -> Option<Operator> {
+        match self.peek_token() {
+            Some(Ok(token)) => Operator::try_from(token).ok(),
+            _ => None,
+        }
+    }
+
+    /// The type of a literal written as a type keyword followed by a string, like
+    /// `date "2024-01-31"`.
+    fn peek_literal_ty(&mut self) -> Option<Ty> {
+        Some(match self.peek_token() {
+            Some(Ok(Token::Keyword(Keyword::Date))) => Ty::Date,
+            Some(Ok(Token::Keyword(Keyword::Time))) => Ty::Time,
+            Some(Ok(Token::Keyword(Keyword::Timestamp))) => Ty::Timestamp,
+            Some(Ok(Token::Keyword(Keyword::Interval))) => Ty::Interval,
+            _ => return None,
+        })
+    }
+
+    /// Parses the string of a literal after its type keyword.
+    fn next_literal(&mut self, ty: Ty) -> Result<Value, ParseError> {
+        let literal = crate::expect_token! {
+            self.next_token(),
+            "<string>",
+            Token::StrLiteral(literal) => literal
+        }?;
+
+        let value = match ty {
+            Ty::Date => datetime::parse_date(&literal).map(Value::Date),
+            Ty::Time => datetime::parse_time(&literal).map(Value::Time),
+            Ty::Timestamp => datetime::parse_timestamp(&literal).map(Value::Timestamp),
+            Ty::Interval => datetime::parse_interval(&literal).map(Value::Interval),
+            _ => unreachable!("not a literal type"),
+        };
+
+        value.ok_or(ParseError::InvalidLiteral { ty, literal })
+    }
+
+    /// Parses an identifier, which can also be written as a string or be a keyword that is
+    /// only reserved in other places.
+    fn next_identifier(&mut self, expected: &str) -> Result<String, ParseError> {
+        crate::expect_token! {
+            self.next_token(),
+            expected,
+            Token::Identifier(identifier) | Token::StrLiteral(identifier) => identifier,
+            Token::Keyword(keyword) => Self::keyword_identifier(keyword, expected)?,
+        }
+    }
+
+    fn keyword_identifier(keyword: Keyword, expected: &str) -> Result<String, ParseError> {
+        keyword
+            .as_identifier()
+            .map(str::to_owned)
+            .ok_or_else(|| ParseError::ExpectedToken(expected.to_owned()))
+    }
+
+    fn next_value(&mut self) -> Result<Value, ParseError> {
+        if let Some(ty) = self.peek_literal_ty() {
+            _ = self.next_token();
+            return self.next_literal(ty);
+        }
+
+        match self.peek_token() {
+            Some(Ok(Token::LeftSquare)) => {
+                _ = self.next_token();
+                return self.next_list();
+            }
+            Some(Ok(Token::LeftCurly)) => {
+                _ = self.next_token();
+                return self.next_doc();
+            }
+            _ => {}
+        }
+
+        let Some(value) = self.peek_value() else {
+            return Err(match self.next_token() {
+                Some(Err(err)) => err.into(),
+                _ => ParseError::ExpectedToken("value".to_owned()),
+            });
+        };
+
+        _ = self.next_token();
+        Ok(value)
+    }
+
+    /// Parses an expression that has to be a value, like elements of list and document literals.
+    fn next_constant(&mut self) -> Result<Value, ParseError> {
+        match self.next_single_expression()? {
+            Expression::Value(value) => Ok(value),
+            _ => Err(ParseError::ExpectedToken("value".to_owned())),
+        }
+    }
+
+    /// Parses the values of a list literal up to and including the closing `]`.
+    fn next_list(&mut self) -> Result<Value, ParseError> {
+        let mut values = Vec::new();
+        while !matches!(self.peek_token(), Some(Ok(Token::RightSquare)) | None) {
+            if !values.is_empty() {
+                crate::expect_token! {
+                    self.next_token(),
+                    ",",
+                    Token::Comma => {}
+                }?;
+            }
+
+            values.push(self.next_constant()?);
+        }
+
+        crate::expect_token! {
+            self.next_token(),
+            "]",
+            Token::RightSquare => {}
+        }?;
+
+        Ok(Value::List(values))
+    }
+
+    /// Parses the fields of a document literal, written like JSON, up to and including the
+    /// closing `}`.
+    fn next_doc(&mut self) -> Result<Value, ParseError> {
+        let mut fields = BTreeMap::new();
+        while !matches!(self.peek_token(), Some(Ok(Token::RightCurly)) | None) {
+            if !fields.is_empty() {
+                crate::expect_token! {
+                    self.next_token(),
+                    ",",
+                    Token::Comma => {}
+                }?;
+            }
+
+            let key = self.next_identifier("<key>")?;
+
+            crate::expect_token! {
+                self.next_token(),
+                ":",
+                Token::Colon => {}
+            }?;
+
+            let value = self.next_constant()?;
+            fields.insert(key, value);
+        }
+
+        crate::expect_token! {
+            self.next_token(),
+            "}",
+            Token::RightCurly => {}
+        }?;
+
+        Ok(Value::Doc(fields))
+    }
+
     fn next_single_expression(&mut self) -> Result<Expression, ParseError> {
-        if let Some(value) = self.peek_value() {
+        if let Some(ty) = self.peek_literal_ty() {
+            let Some(Ok(Token::Keyword(keyword))) = self.next_token() else {
+                unreachable!();
+            };
+
+            // Without its string the type keyword is a column, like `date > date "2024-01-31"`.
+            if let Some(Ok(Token::StrLiteral(_))) = self.peek_token() {
+                return Ok(Expression::Value(self.next_literal(ty)?));
+            }
+
+            let identifier = Self::keyword_identifier(keyword, "expression")?;
+            return Ok(Expression::Identifier(identifier));
+        }
+
+        if self.peek_value().is_some()
+            || matches!(
+                self.peek_token(),
+                Some(Ok(Token::LeftSquare | Token::LeftCurly))
+            )
+        {
+            return Ok(Expression::Value(self.next_value()?));
+        }
+
+        if let Some(Ok(Token::Minus)) = self.peek_token() {
             _ = self.next_token();
-            return Ok(Expression::Value(value));
+            // Binds tighter than any operator but indexing, negative numbers are literals.
+            return Ok(
+                match self.next_bound_expression(Operator::Index.precedence())? {
+                    Expression::Value(Value::Int(int)) => Expression::Value(Value::Int(-int)),
+                    Expression::Value(Value::Float(float)) => {
+                        Expression::Value(Value::Float(-float))
+                    }
+                    Expression::Value(Value::Decimal(decimal)) => Expression::Value(
+                        Value::Decimal(Decimal::new(-decimal.mantissa(), decimal.scale())),
+                    ),
+                    expression => Expression::Enclosed(Box::new(Expression::Operation {
+                        lhs: Box::new(Expression::Value(Value::Int(-1))),
+                        operator: Operator::Mul,
+                        rhs: Box::new(expression),
+                    })),
+                },
+            );
         }
 
         crate::expect_token! {
             self.next_token(),
             "expression",
-            Token::Identifier(identifier)
-                | Token::StrLiteral(identifier) => Expression::Identifier(identifier),
+            Token::Identifier(identifier) => {
+                if identifier == "null" {
+                    // Like in JSON.
+                    Expression::Value(Value::Nil)
+                } else if let Some(Ok(Token::LeftSmooth)) = self.peek_token() {
+                    _ = self.next_token();
+                    Expression::Call {
+                        identifier,
+                        arguments: self.next_arguments()?,
+                    }
+                } else {
+                    Expression::Identifier(identifier)
+                }
+            },
+            Token::StrLiteral(identifier) => Expression::Identifier(identifier),
+            Token::Keyword(Keyword::Cast) => {
+                crate::expect_token! {
+                    self.next_token(),
+                    "(",
+                    Token::LeftSmooth => {}
+                }?;
+
+                let expression = self.next_expression()?;
+                crate::expect_token! {
+                    self.next_token(),
+                    "as",
+                    Token::Keyword(Keyword::As) => {}
+                }?;
+
+                let ty = self.next_ty()?;
+                crate::expect_token! {
+                    self.next_token(),
+                    ")",
+                    Token::RightSmooth => {}
+                }?;
+
+                Expression::Cast {
+                    expression: Box::new(expression),
+                    ty,
+                }
+            },
+            // Also an operator, but it can only be the function in place of an operand.
+            Token::Keyword(Keyword::Contains) => {
+                crate::expect_token! {
+                    self.next_token(),
+                    "(",
+                    Token::LeftSmooth => {}
+                }?;
+
+                Expression::Call {
+                    identifier: "contains".to_owned(),
+                    arguments: self.next_arguments()?,
+                }
+            },
+            Token::Keyword(Keyword::Get) => {
+                let command = self.next_command(Ok(Token::Keyword(Keyword::Get)))?;
+                Expression::Query(Box::new(command))
+            },
             Token::LeftSmooth => {
                 let expression = self.next_expression()?;
                 crate::expect_token! {
@@ -82,32 +345,392 @@ impl<'a> CommandIter<'a> {
                     Token::RightSmooth => {}
                 }?;
 
-                expression
+                Expression::Enclosed(Box::new(expression))
+            },
+            Token::Keyword(keyword) => {
+                Expression::Identifier(Self::keyword_identifier(keyword, "expression")?)
+            },
+        }
+    }
+
+    /// Parses comma separated expressions up to and including the closing `)`.
+    fn next_arguments(&mut self) -> Result<Vec<Expression>, ParseError> {
+        let mut arguments = Vec::new();
+        while !matches!(self.peek_token(), Some(Ok(Token::RightSmooth)) | None) {
+            if !arguments.is_empty() {
+                crate::expect_token! {
+                    self.next_token(),
+                    ",",
+                    Token::Comma => {}
+                }?;
+            }
+
+            arguments.push(self.next_expression()?);
+        }
+
+        crate::expect_token! {
+            self.next_token(),
+            ")",
+            Token::RightSmooth => {}
+        }?;
+
+        Ok(arguments)
+    }
+
+    fn next_ty(&mut self) -> Result<TyDefinition, ParseError> {
+        crate::expect_token! {
+            self.next_token(),
+            "<type>",
+            Token::Keyword(Keyword::Str) => TyDefinition::Ty(Ty::Str),
+            Token::Keyword(Keyword::Int) => TyDefinition::Ty(Ty::Int),
+            Token::Keyword(Keyword::Float) => TyDefinition::Ty(Ty::Float),
+            Token::Keyword(Keyword::Bool) => TyDefinition::Ty(Ty::Bool),
+            Token::Keyword(Keyword::Bytes) => TyDefinition::Ty(Ty::Bytes),
+            Token::Keyword(Keyword::Decimal) => TyDefinition::Ty(self.next_decimal_ty()?),
+            Token::Keyword(Keyword::Date) => TyDefinition::Ty(Ty::Date),
+            Token::Keyword(Keyword::Time) => TyDefinition::Ty(Ty::Time),
+            Token::Keyword(Keyword::Timestamp) => TyDefinition::Ty(Ty::Timestamp),
+            Token::Keyword(Keyword::Interval) => TyDefinition::Ty(Ty::Interval),
+            Token::Keyword(Keyword::Doc) => TyDefinition::Ty(Ty::Doc),
+            Token::Identifier(identifier)
+                | Token::StrLiteral(identifier) => TyDefinition::Named(identifier),
+            Token::Keyword(keyword) => {
+                TyDefinition::Named(Self::keyword_identifier(keyword, "<type>")?)
+            },
+            Token::LeftSquare => {
+                let ty = self.next_ty()?;
+                crate::expect_token! {
+                    self.next_token(),
+                    "]",
+                    Token::RightSquare => {}
+                }?;
+
+                TyDefinition::List(Box::new(ty))
+            },
+        }
+    }
+
+    /// Parses the `enum ("A", "B")` part of a `new type` command.
+    fn next_enum(&mut self, identifier: String) -> Result<Command, ParseError> {
+        crate::expect_token! {
+            self.next_token(),
+            "enum",
+            Token::Keyword(Keyword::Enum) => {}
+        }?;
+
+        crate::expect_token! {
+            self.next_token(),
+            "(",
+            Token::LeftSmooth => {}
+        }?;
+
+        let mut variants = Vec::new();
+        loop {
+            variants.push(crate::expect_token! {
+                self.next_token(),
+                "<variant>",
+                Token::StrLiteral(variant) => variant
+            }?);
+
+            let end = crate::expect_token! {
+                self.next_token(),
+                ", or )",
+                Token::Comma => false,
+                Token::RightSmooth => true,
+            }?;
+
+            if end {
+                break;
+            }
+        }
+
+        Ok(Command::NewEnum {
+            identifier,
+            variants,
+        })
+    }
+
+    /// Parses the `(precision, scale)` part of a decimal type, the scale can be left out.
+    fn next_decimal_ty(&mut self) -> Result<Ty, ParseError> {
+        crate::expect_token! {
+            self.next_token(),
+            "(",
+            Token::LeftSmooth => {}
+        }?;
+
+        let precision = crate::expect_token! {
+            self.next_token(),
+            "<precision>",
+            Token::IntLiteral(int) => int
+        }?;
+
+        let scale = crate::expect_token! {
+            self.next_token(),
+            ", or )",
+            Token::Comma => {
+                let scale = crate::expect_token! {
+                    self.next_token(),
+                    "<scale>",
+                    Token::IntLiteral(int) => int
+                }?;
+
+                crate::expect_token! {
+                    self.next_token(),
+                    ")",
+                    Token::RightSmooth => {}
+                }?;
+
+                scale
+            },
+            Token::RightSmooth => 0,
+        }?;
+
+        if !(1..=MAX_PRECISION as i64).contains(&precision) || !(0..=precision).contains(&scale) {
+            return Err(ParseError::InvalidPrecision);
+        }
+
+        Ok(Ty::Decimal {
+            precision: precision as u8,
+            scale: scale as u8,
+        })
+    }
+
+    /// Parses the columns of a `primary key (A, B)` declaration, after `primary`.
+    fn next_key(&mut self) -> Result<Vec<String>, ParseError> {
+        crate::expect_token! {
+            self.next_token(),
+            "key",
+            Token::Keyword(Keyword::Key) => {}
+        }?;
+
+        crate::expect_token! {
+            self.next_token(),
+            "(",
+            Token::LeftSmooth => {}
+        }?;
+
+        let mut columns = Vec::new();
+        loop {
+            columns.push(self.next_identifier("<identifier>")?);
+
+            if crate::expect_token! {
+                self.next_token(),
+                ", or )",
+                Token::Comma => false,
+                Token::RightSmooth => true,
+            }? {
+                break;
             }
         }
+
+        Ok(columns)
+    }
+
+    /// Parses a selection following the `selections` before it, expressions without a name are
+    /// named so that they do not repeat those names, like `upper` and `upper_2`.
+    fn next_selection(&mut self, selections: &[Selection]) -> Result<Selection, ParseError> {
+        let expression = match self.peek_token() {
+            Some(Ok(Token::Star)) => {
+                _ = self.next_token();
+                return Ok(Selection::All);
+            }
+            Some(Ok(Token::StrLiteral(_))) => {
+                let Some(Ok(Token::StrLiteral(identifier))) = self.next_token() else {
+                    unreachable!();
+                };
+
+                Expression::Identifier(identifier)
+            }
+            _ => self.next_expression()?,
+        };
+
+        let alias = match self.peek_token() {
+            Some(Ok(Token::Keyword(Keyword::As))) => {
+                _ = self.next_token();
+                Some(self.next_identifier("<identifier>")?)
+            }
+            _ => None,
+        };
+
+        Ok(match (expression, alias) {
+            (Expression::Identifier(identifier), None) => Selection::Identifier { identifier },
+            (expression, Some(identifier)) => Selection::Expression {
+                expression,
+                identifier,
+            },
+            (expression, None) => {
+                let identifier = match &expression {
+                    Expression::Call { identifier, .. } => identifier.clone(),
+                    // Fields of documents are named after the last field, however it is written.
+                    Expression::Operation {
+                        operator: Operator::Index,
+                        rhs,
+                        ..
+                    } => {
+                        let index = match &**rhs {
+                            Expression::Enclosed(index) => &**index,
+                            index => index,
+                        };
+
+                        match index {
+                            Expression::Value(Value::Str(field)) => field.clone(),
+                            _ => "?column?".to_owned(),
+                        }
+                    }
+                    _ => "?column?".to_owned(),
+                };
+
+                let taken = |identifier: &str| {
+                    selections.iter().any(|selection| match selection {
+                        Selection::Identifier { identifier: taken }
+                        | Selection::Expression {
+                            identifier: taken, ..
+                        } => taken == identifier,
+                        Selection::All => false,
+                    })
+                };
+
+                let mut unique = identifier.clone();
+                for index in 2.. {
+                    if !taken(&unique) {
+                        break;
+                    }
+
+                    unique = format!("{identifier}_{index}");
+                }
+
+                Selection::Expression {
+                    expression,
+                    identifier: unique,
+                }
+            }
+        })
     }
 
     fn next_expression(&mut self) -> Result<Expression, ParseError> {
+        self.next_bound_expression(0)
+    }
+
+    /// Parses an expression made only of operators with a precedence of at least `precedence`.
+    fn next_bound_expression(&mut self, precedence: u8) -> Result<Expression, ParseError> {
+        // "in" and "between" bind like comparisons.
+        let comparison = Operator::Eq.precedence();
+
         let mut expression = self.next_single_expression()?;
         loop {
-            let operator = match self.peek_token() {
-                Some(Ok(token)) => match Operator::try_from(token) {
-                    Ok(operator) => {
-                        _ = self.next_token();
-                        operator
+            if let Some(Ok(Token::Keyword(Keyword::In | Keyword::Not | Keyword::Between))) =
+                self.peek_token()
+            {
+                if comparison < precedence {
+                    break;
+                }
+
+                let negated = matches!(self.peek_token(), Some(Ok(Token::Keyword(Keyword::Not))));
+                if negated {
+                    _ = self.next_token();
+                }
+
+                expression = crate::expect_token! {
+                    self.next_token(),
+                    "in or between",
+                    Token::Keyword(Keyword::In) => {
+                        crate::expect_token! {
+                            self.next_token(),
+                            "(",
+                            Token::LeftSmooth => {}
+                        }?;
+
+                        let list = self.next_arguments()?;
+                        expression.extended_with(comparison, |expression| Expression::In {
+                            expression: Box::new(expression),
+                            list,
+                            negated,
+                        })
+                    },
+                    Token::Keyword(Keyword::Between) => {
+                        let low = self.next_bound_expression(comparison + 1)?;
+                        crate::expect_token! {
+                            self.next_token(),
+                            "and",
+                            Token::Keyword(Keyword::And) => {}
+                        }?;
+
+                        let high = self.next_bound_expression(comparison + 1)?;
+                        expression.extended_with(comparison, |expression| Expression::Between {
+                            expression: Box::new(expression),
+                            low: Box::new(low),
+                            high: Box::new(high),
+                            negated,
+                        })
+                    },
+                }?;
+
+                continue;
+            }
+
+            if let Some(Ok(Token::DoubleColon)) = self.peek_token() {
+                // Binds as tightly as indexing.
+                _ = self.next_token();
+                let ty = self.next_ty()?;
+                expression = expression.extended_with(Operator::Index.precedence(), |expression| {
+                    Expression::Cast {
+                        expression: Box::new(expression),
+                        ty,
                     }
-                    Err(_) => break,
-                },
-                _ => break,
+                });
+                continue;
+            }
+
+            if let Some(Ok(Token::Dot)) = self.peek_token() {
+                // A field of a document, `doc.field` is the same as `doc["field"]`.
+                _ = self.next_token();
+                let field = self.next_identifier("<field>")?;
+
+                expression =
+                    expression.extended(Operator::Index, Expression::Value(Value::Str(field)));
+                continue;
+            }
+
+            let Some(operator) = self.peek_operator() else {
+                break;
             };
 
+            if operator.precedence() < precedence {
+                break;
+            }
+
+            _ = self.next_token();
+            if let Operator::StartsWith
+            | Operator::IStartsWith
+            | Operator::EndsWith
+            | Operator::IEndsWith = operator
+            {
+                crate::expect_token! {
+                    self.next_token(),
+                    "with",
+                    Token::Keyword(Keyword::With) => {}
+                }?;
+            }
+
+            if let Operator::Index = operator {
+                let index = self.next_expression()?;
+                crate::expect_token! {
+                    self.next_token(),
+                    "]",
+                    Token::RightSquare => {}
+                }?;
+
+                expression = expression.extended(operator, Expression::Enclosed(Box::new(index)));
+                continue;
+            }
+
             expression = expression.extended(operator, self.next_single_expression()?);
         }
 
         Ok(expression)
     }
 
-    fn next_insertion(&mut self) -> Result<HashMap<String, Value>, ParseError> {
+    fn next_insertion(&mut self) -> Result<HashMap<String, Expression>, ParseError> {
         let mut insertion = HashMap::new();
         while let Some(token) = self.peek_token() {
             if let Ok(Token::SemiColon) = token {
@@ -122,12 +745,7 @@ impl<'a> CommandIter<'a> {
                 _ = self.next_token();
             }
 
-            let identifier = crate::expect_token! {
-                self.next_token(),
-                "<identifier>",
-                Token::Identifier(identifier)
-                    | Token::StrLiteral(identifier) => identifier
-            }?;
+            let identifier = self.next_identifier("<identifier>")?;
 
             crate::expect_token! {
                 self.next_token(),
@@ -135,18 +753,59 @@ impl<'a> CommandIter<'a> {
                 Token::Colon => {}
             }?;
 
-            let Some(value) = self.peek_value() else {
-                _ = self.next_token();
-                return Err(ParseError::ExpectedToken("value".to_owned()));
-            };
-
-            _ = self.next_token();
-            insertion.insert(identifier, value);
+            insertion.insert(identifier, self.next_expression()?);
         }
 
         Ok(insertion)
     }
 
+    /// Parses a "get" command following the "get" keyword.
+    fn next_get(&mut self) -> Result<Command, ParseError> {
+        let identifier = self.next_identifier("<identifier>")?;
+
+        let mut distinct = false;
+        let selections = match self.peek_token() {
+            Some(Ok(Token::Keyword(Keyword::Select))) => {
+                _ = self.next_token();
+                if let Some(Ok(Token::Keyword(Keyword::Distinct))) = self.peek_token() {
+                    _ = self.next_token();
+                    distinct = true;
+                }
+
+                let mut selections = Vec::new();
+                while let Some(token) = self.peek_token() {
+                    if !selections.is_empty() {
+                        let Ok(Token::Comma) = token else {
+                            break;
+                        };
+
+                        _ = self.next_token();
+                    }
+
+                    selections.push(self.next_selection(&selections)?);
+                }
+
+                selections
+            }
+            _ => vec![Selection::All],
+        };
+
+        let filter = match self.peek_token() {
+            Some(Ok(Token::Keyword(Keyword::Where))) => {
+                _ = self.next_token();
+                Some(self.next_expression()?)
+            }
+            _ => None,
+        };
+
+        Ok(Command::Get {
+            identifier,
+            selections,
+            distinct,
+            filter,
+        })
+    }
+
     fn next_command(&mut self, token: Result<Token, TokenizeError>) -> Result<Command, ParseError> {
         let Token::Keyword(keyword) = token? else {
             return Err(ParseError::ExpectedToken(
@@ -156,70 +815,69 @@ impl<'a> CommandIter<'a> {
 
         match keyword {
             Keyword::Get => {
-                let identifier = crate::expect_token! {
-                    self.next_token(),
-                    "<identifier>",
-                    Token::Identifier(identifier)
-                        | Token::StrLiteral(identifier)  => identifier
-                }?;
-
-                let selections = match self.peek_token() {
-                    Some(Ok(Token::Keyword(Keyword::Select))) => {
-                        _ = self.next_token();
-
-                        let mut selections = Vec::new();
-                        while let Some(token) = self.peek_token() {
-                            if !selections.is_empty() {
-                                let Ok(Token::Comma) = token else {
-                                    break;
-                                };
-
-                                _ = self.next_token();
-                            }
+                let mut command = self.next_get()?;
+                while let Some(Ok(Token::Keyword(
+                    keyword @ (Keyword::Union | Keyword::Intersect | Keyword::Except),
+                ))) = self.peek_token()
+                {
+                    let operation = match keyword {
+                        Keyword::Union => SetOperation::Union,
+                        Keyword::Intersect => SetOperation::Intersect,
+                        _ => SetOperation::Except,
+                    };
 
-                            selections.push(crate::expect_token! {
-                                self.next_token(),
-                                "* or <column name> or @<row attribute>",
-                                Token::Identifier(identifier)
-                                    | Token::StrLiteral(identifier) => {
-                                    Selection::Identifier { identifier }
-                                },
-                                Token::Star => Selection::All,
-                            }?);
+                    _ = self.next_token();
+                    let operation = match (operation, self.peek_token()) {
+                        (SetOperation::Union, Some(Ok(Token::Keyword(Keyword::All)))) => {
+                            _ = self.next_token();
+                            SetOperation::UnionAll
                         }
+                        (operation, _) => operation,
+                    };
 
-                        selections
-                    }
-                    _ => vec![Selection::All],
-                };
+                    crate::expect_token! {
+                        self.next_token(),
+                        "get",
+                        Token::Keyword(Keyword::Get) => {}
+                    }?;
 
-                let filter = match self.peek_token() {
-                    Some(Ok(Token::Keyword(Keyword::Where))) => {
-                        _ = self.next_token();
-                        Some(self.next_expression()?)
-                    }
-                    _ => None,
-                };
+                    command = Command::Combine {
+                        lhs: Box::new(command),
+                        operation,
+                        rhs: Box::new(self.next_get()?),
+                    };
+                }
 
-                Ok(Command::Get {
-                    identifier,
-                    selections,
-                    filter,
-                })
+                Ok(command)
             }
             Keyword::New => {
-                crate::expect_token! {
+                let is_type = crate::expect_token! {
                     self.next_token(),
-                    "table",
-                    Token::Keyword(Keyword::Table) => {}
+                    "table or type",
+                    Token::Keyword(Keyword::Table) => false,
+                    Token::Keyword(Keyword::Type) => true,
                 }?;
 
-                let identifier = crate::expect_token! {
-                    self.next_token(),
-                    "<identifier>",
-                    Token::Identifier(identifier)
-                        | Token::StrLiteral(identifier) => identifier
-                }?;
+                let identifier = self.next_identifier("<identifier>")?;
+
+                if is_type {
+                    return self.next_enum(identifier);
+                }
+
+                let mut key = Key::Id;
+                if matches!(
+                    self.peek_token(),
+                    Some(Ok(Token::Keyword(Keyword::Without)))
+                ) {
+                    _ = self.next_token();
+                    crate::expect_token! {
+                        self.next_token(),
+                        "key",
+                        Token::Keyword(Keyword::Key) => {}
+                    }?;
+
+                    key = Key::None;
+                }
 
                 let mut definitions = Vec::new();
                 while let Some(token) = self.peek_token() {
@@ -227,7 +885,7 @@ impl<'a> CommandIter<'a> {
                         break;
                     }
 
-                    if !definitions.is_empty() {
+                    if !definitions.is_empty() || matches!(key, Key::Primary(_)) {
                         let Ok(Token::Comma) = token else {
                             return Err(ParseError::ExpectedToken(",".to_owned()));
                         };
@@ -235,12 +893,19 @@ impl<'a> CommandIter<'a> {
                         _ = self.next_token();
                     }
 
-                    let identifier = crate::expect_token! {
-                        self.next_token(),
-                        "<identifier>",
-                        Token::Identifier(identifier)
-                            | Token::StrLiteral(identifier) => identifier
-                    }?;
+                    // Only a table with the default key can declare a primary one, once.
+                    if key == Key::Id
+                        && matches!(
+                            self.peek_token(),
+                            Some(Ok(Token::Keyword(Keyword::Primary)))
+                        )
+                    {
+                        _ = self.next_token();
+                        key = Key::Primary(self.next_key()?);
+                        continue;
+                    }
+
+                    let identifier = self.next_identifier("<identifier>")?;
 
                     crate::expect_token! {
                         self.next_token(),
@@ -248,14 +913,7 @@ impl<'a> CommandIter<'a> {
                         Token::Colon => {}
                     }?;
 
-                    let ty = crate::expect_token! {
-                        self.next_token(),
-                        "<type>",
-                        Token::Keyword(Keyword::Str) => Ty::Str,
-                        Token::Keyword(Keyword::Int) => Ty::Int,
-                        Token::Keyword(Keyword::Float) => Ty::Float,
-                        Token::Keyword(Keyword::Bool) => Ty::Bool,
-                    }?;
+                    let ty = self.next_ty()?;
 
                     let optional = if matches!(self.peek_token(), Some(Ok(Token::QuestionMark))) {
                         _ = self.next_token();
@@ -264,28 +922,109 @@ impl<'a> CommandIter<'a> {
                         false
                     };
 
+                    let mut unique = false;
+                    let mut default = None;
+                    let mut check = None;
+                    let mut references = None;
+                    loop {
+                        match self.peek_token() {
+                            Some(Ok(Token::Keyword(Keyword::Unique))) => {
+                                _ = self.next_token();
+                                unique = true;
+                            }
+                            Some(Ok(Token::Eq)) => {
+                                _ = self.next_token();
+                                default = Some(self.next_constant()?);
+                            }
+                            Some(Ok(Token::Keyword(Keyword::Check))) => {
+                                _ = self.next_token();
+                                crate::expect_token! {
+                                    self.next_token(),
+                                    "(",
+                                    Token::LeftSmooth => {}
+                                }?;
+
+                                check = Some(self.next_expression()?);
+                                crate::expect_token! {
+                                    self.next_token(),
+                                    ")",
+                                    Token::RightSmooth => {}
+                                }?;
+                            }
+                            Some(Ok(Token::Keyword(Keyword::References))) => {
+                                _ = self.next_token();
+                                let table = self.next_identifier("<identifier>")?;
+
+                                let on_remove = if matches!(
+                                    self.peek_token(),
+                                    Some(Ok(Token::Keyword(Keyword::On)))
+                                ) {
+                                    _ = self.next_token();
+                                    crate::expect_token! {
+                                        self.next_token(),
+                                        "remove",
+                                        Token::Keyword(Keyword::Remove) => {}
+                                    }?;
+
+                                    crate::expect_token! {
+                                        self.next_token(),
+                                        "restrict, cascade or set",
+                                        Token::Keyword(Keyword::Restrict) => OnRemove::Restrict,
+                                        Token::Keyword(Keyword::Cascade) => OnRemove::Cascade,
+                                        Token::Keyword(Keyword::Set) => {
+                                            crate::expect_token! {
+                                                self.next_token(),
+                                                "nil",
+                                                Token::Keyword(Keyword::Nil) => OnRemove::SetNil
+                                            }?
+                                        },
+                                    }?
+                                } else {
+                                    OnRemove::Restrict
+                                };
+
+                                references = Some(Reference { table, on_remove });
+                            }
+                            _ => break,
+                        }
+                    }
+
                     definitions.push(ColumnDefinition {
                         identifier,
                         optional,
                         ty,
+                        unique,
+                        default,
+                        check,
+                        references,
                     });
                 }
 
                 Ok(Command::New {
                     identifier,
                     definitions,
+                    key,
                 })
             }
             Keyword::Insert => {
-                let identifier = crate::expect_token! {
-                    self.next_token(),
-                    "<identifier>",
-                    Token::Identifier(identifier)
-                        | Token::StrLiteral(identifier) => identifier
-                }?;
+                let identifier = self.next_identifier("<identifier>")?;
 
                 let mut insertions = Vec::new();
                 match self.peek_token() {
+                    Some(Ok(Token::Keyword(Keyword::From))) => {
+                        _ = self.next_token();
+                        crate::expect_token! {
+                            self.next_token(),
+                            "get",
+                            Token::Keyword(Keyword::Get) => {}
+                        }?;
+
+                        let query = self.next_command(Ok(Token::Keyword(Keyword::Get)))?;
+                        return Ok(Command::InsertFrom {
+                            identifier,
+                            query: Box::new(query),
+                        });
+                    }
                     Some(Ok(Token::LeftCurly)) => {
                         _ = self.next_token();
                         while !matches!(self.peek_token(), Some(Ok(Token::RightCurly)) | None) {
@@ -312,12 +1051,7 @@ impl<'a> CommandIter<'a> {
                 })
             }
             Keyword::Remove => {
-                let identifier = crate::expect_token! {
-                    self.next_token(),
-                    "<identifier>",
-                    Token::Identifier(identifier)
-                        | Token::StrLiteral(identifier) => identifier
-                }?;
+                let identifier = self.next_identifier("<identifier>")?;
 
                 crate::expect_token! {
                     self.next_token(),
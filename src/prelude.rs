@@ -1 +1,8 @@
-pub use crate::{database::*, lexer::TokenIter, parser::CommandIter};
+pub use crate::{
+    cast::CastError,
+    database::*,
+    decimal::Decimal,
+    function::{FunctionCallError, Functions, Signature},
+    lexer::TokenIter,
+    parser::CommandIter,
+};
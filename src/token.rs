@@ -1,21 +1,30 @@
 use std::str::FromStr;
 
+use crate::decimal::Decimal;
+
 #[derive(Debug, Clone)]
 pub enum Token {
     StrLiteral(String),
-    IntLiteral(i32),
-    FloatLiteral(f32),
+    IntLiteral(i64),
+    FloatLiteral(f64),
+    BytesLiteral(Vec<u8>),
+    DecimalLiteral(Decimal),
     Identifier(String),
     Keyword(Keyword),
     Comma,
     Colon,
+    DoubleColon,
     SemiColon,
     At,
     LeftSmooth,
     RightSmooth,
     LeftCurly,
     RightCurly,
+    LeftSquare,
+    RightSquare,
+    Dot,
     QuestionMark,
+    Eq,
     // Operators
     // ---------@
     DoubleEq,
@@ -35,18 +44,57 @@ pub enum Token {
 pub enum Keyword {
     Get,
     Select,
+    Distinct,
+    Union,
+    All,
+    Intersect,
+    Except,
     Table,
     New,
     Insert,
     As,
     Where,
     Remove,
+    Like,
+    ILike,
+    Contains,
+    IContains,
+    Starts,
+    IStarts,
+    Ends,
+    IEnds,
+    With,
+    In,
+    Not,
+    Between,
+    And,
+    Unique,
+    Check,
+    References,
+    On,
+    Restrict,
+    Cascade,
+    Set,
+    Primary,
+    Key,
+    Without,
+    Type,
+    Enum,
+    Cast,
+    From,
     // Types
     // -----@
     Str,
     Int,
     Float,
     Bool,
+    Bytes,
+    Decimal,
+    Date,
+    Time,
+    Timestamp,
+    Interval,
+    Doc,
     Nil,
     // Bool literals
     // -------------@
@@ -54,6 +102,35 @@ pub enum Keyword {
     False,
 }
 
+impl Keyword {
+    /// The keyword as an identifier, if it is only reserved where the parser expects it. Column,
+    /// table and type names can be words like `date` or `key` that way. Keywords that would make
+    /// a command ambiguous, like `primary`, `without`, `from`, `cast` or operators, stay
+    /// reserved.
+    pub(crate) fn as_identifier(self) -> Option<&'static str> {
+        Some(match self {
+            Keyword::Unique => "unique",
+            Keyword::Check => "check",
+            Keyword::References => "references",
+            Keyword::On => "on",
+            Keyword::Restrict => "restrict",
+            Keyword::Cascade => "cascade",
+            Keyword::Set => "set",
+            Keyword::Key => "key",
+            Keyword::Type => "type",
+            Keyword::Enum => "enum",
+            Keyword::Bytes => "bytes",
+            Keyword::Decimal => "decimal",
+            Keyword::Date => "date",
+            Keyword::Time => "time",
+            Keyword::Timestamp => "timestamp",
+            Keyword::Interval => "interval",
+            Keyword::Doc => "doc",
+            _ => return None,
+        })
+    }
+}
+
 #[derive(Debug, Clone, Copy)]
 pub struct NoSuchKeywordError;
 impl FromStr for Keyword {
@@ -63,12 +140,44 @@ impl FromStr for Keyword {
         Ok(match s {
             "get" => Keyword::Get,
             "select" => Keyword::Select,
+            "distinct" => Keyword::Distinct,
+            "union" => Keyword::Union,
+            "all" => Keyword::All,
+            "intersect" => Keyword::Intersect,
+            "except" => Keyword::Except,
             "table" => Keyword::Table,
             "new" => Keyword::New,
             "insert" => Keyword::Insert,
             "as" => Keyword::As,
             "where" => Keyword::Where,
             "remove" => Keyword::Remove,
+            "like" => Keyword::Like,
+            "ilike" => Keyword::ILike,
+            "contains" => Keyword::Contains,
+            "icontains" => Keyword::IContains,
+            "starts" => Keyword::Starts,
+            "istarts" => Keyword::IStarts,
+            "ends" => Keyword::Ends,
+            "iends" => Keyword::IEnds,
+            "with" => Keyword::With,
+            "in" => Keyword::In,
+            "not" => Keyword::Not,
+            "between" => Keyword::Between,
+            "and" => Keyword::And,
+            "unique" => Keyword::Unique,
+            "check" => Keyword::Check,
+            "references" => Keyword::References,
+            "on" => Keyword::On,
+            "restrict" => Keyword::Restrict,
+            "cascade" => Keyword::Cascade,
+            "set" => Keyword::Set,
+            "primary" => Keyword::Primary,
+            "key" => Keyword::Key,
+            "without" => Keyword::Without,
+            "type" => Keyword::Type,
+            "enum" => Keyword::Enum,
+            "cast" => Keyword::Cast,
+            "from" => Keyword::From,
             // Types
             // -----@
             "str" => Keyword::Str,
@@ -76,6 +185,13 @@ impl FromStr for Keyword {
             "float" => Keyword::Float,
             "nil" => Keyword::Nil,
             "bool" => Keyword::Bool,
+            "bytes" => Keyword::Bytes,
+            "decimal" => Keyword::Decimal,
+            "date" => Keyword::Date,
+            "time" => Keyword::Time,
+            "timestamp" => Keyword::Timestamp,
+            "interval" => Keyword::Interval,
+            "doc" => Keyword::Doc,
             // Bool literals
             // -------------@
             "true" => Keyword::True,